@@ -0,0 +1,89 @@
+//! Interning new strings from many threads at once, to compare the sharded
+//! `Interner` with an `InternPool`, which has a single lock & pair of tables:
+//!
+//! ```text
+//! cargo bench --bench contention
+//! ```
+//!
+//! Each round interns the same number of distinct strings, split between the
+//! threads, into a fresh pool. A writer of one shard doesn't wait for the
+//! writers of the others, so the `Interner`'s time per string should keep
+//! falling as threads are added, up to the number of cores, while the
+//! `InternPool`'s levels off.
+
+use {
+  ::sinter::{InternPool, Interner},
+  ::std::{
+    hint::black_box,
+    sync::Barrier,
+    thread,
+    time::{Duration, Instant},
+  },
+};
+
+const STRINGS: usize = 400_000;
+const ROUNDS: usize = 5;
+
+/// Run `intern` over every string, split between `threads` threads, and
+/// return the best time of `ROUNDS` rounds. `setup` makes a fresh pool for
+/// each round.
+fn bench<P: Sync>(
+  strings: &[&'static str],
+  threads: usize,
+  setup: impl Fn() -> P,
+  intern: impl Fn(&P, &'static str) + Sync,
+) -> Duration {
+  (0..ROUNDS)
+    .map(|_| {
+      let pool = setup();
+      let started = Barrier::new(threads + 1);
+      thread::scope(|s| {
+        for chunk in strings.chunks(strings.len().div_ceil(threads)) {
+          let (pool, started, intern) = (&pool, &started, &intern);
+          s.spawn(move || {
+            started.wait();
+            for &string in chunk {
+              intern(pool, black_box(string));
+            }
+          });
+        }
+        started.wait();
+        Instant::now()
+      })
+      .elapsed()
+    })
+    .min()
+    .unwrap()
+}
+
+fn main() {
+  let strings: Vec<&'static str> = (0..STRINGS)
+    .map(|i| &*format!("contention_{i}").leak())
+    .collect();
+  let cores = thread::available_parallelism().map_or(1, |n| n.get());
+  println!("{STRINGS} new strings, {cores} cores");
+  println!("{:<8} {:>18} {:>18}", "threads", "Interner", "InternPool");
+
+  let mut threads = 1;
+  while threads <= usize::max(8, cores * 2) {
+    let interner = bench(&strings, threads, Interner::new, |pool, s| {
+      black_box(pool.intern(s));
+    });
+    // note: a pool's values live forever, so each round's pool is leaked
+    let intern_pool = bench(
+      &strings,
+      threads,
+      || &*Box::leak(Box::new(InternPool::<&'static str>::new())),
+      |pool, s| {
+        black_box(pool.intern(s));
+      },
+    );
+    let nanos = |d: Duration| d.as_nanos() as f64 / STRINGS as f64;
+    println!(
+      "{threads:<8} {:>10.1} ns/str {:>10.1} ns/str",
+      nanos(interner),
+      nanos(intern_pool)
+    );
+    threads *= 2;
+  }
+}
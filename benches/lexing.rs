@@ -0,0 +1,110 @@
+//! Interning the tokens of a lexer, to compare the global pool with & without
+//! the `thread-cache` feature:
+//!
+//! ```text
+//! cargo bench --bench lexing
+//! cargo bench --bench lexing --features thread-cache
+//! ```
+//!
+//! The source is a synthetic token stream shaped like real code: punctuation
+//! & keywords are most of the tokens, identifiers follow a long tail, and a
+//! few literals are unique. Each token is interned from a slice of the
+//! source, the way a lexer would.
+//!
+//! A separate `Interner` is never cached, so it's measured alongside as the
+//! baseline.
+
+use {
+  ::sinter::{get_interned, intern, Interner},
+  ::std::{hint::black_box, ops::Range, time::Instant},
+};
+
+const TOKENS: usize = 1_000_000;
+const ROUNDS: usize = 10;
+
+const PUNCTUATION: [&str; 12] =
+  ["(", ")", "{", "}", "[", "]", ";", ",", ".", "::", "=", "->"];
+const KEYWORDS: [&str; 12] = [
+  "fn", "let", "mut", "if", "else", "match", "for", "in", "return", "impl",
+  "pub", "struct",
+];
+const IDENTIFIERS: u64 = 10_000;
+
+/// A source of `TOKENS` tokens separated by spaces, and the span of each token
+fn source() -> (String, Vec<Range<usize>>) {
+  // xorshift, so the source is the same every run
+  let mut state = 0x2545_f491_4f6c_dd1d_u64;
+  let mut random = move || {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state
+  };
+
+  let mut source = String::new();
+  let mut spans = Vec::with_capacity(TOKENS);
+  for literal in 0..TOKENS {
+    let start = source.len();
+    let r = random();
+    match r % 100 {
+      0..=44 => source.push_str(PUNCTUATION[(r >> 8) as usize % 12]),
+      45..=69 => source.push_str(KEYWORDS[(r >> 8) as usize % 12]),
+      // note: the identifiers are log-uniform, so that each is about half
+      // as common as the one half its rank, like the words of a text
+      70..=98 => {
+        let u = (r >> 11) as f64 / (1u64 << 53) as f64;
+        let n = (IDENTIFIERS as f64).powf(u) as u64 - 1;
+        source.push_str(&format!("identifier_{n}"));
+      },
+      _ => source.push_str(&format!("{literal}")),
+    }
+    spans.push(start..source.len());
+    source.push(' ');
+  }
+  (source, spans)
+}
+
+/// Run `intern` over every token, and print the best time per token
+fn bench(
+  name: &str,
+  source: &str,
+  spans: &[Range<usize>],
+  intern: impl Fn(&str),
+) {
+  let best = (0..ROUNDS)
+    .map(|_| {
+      let start = Instant::now();
+      for span in spans {
+        intern(black_box(&source[span.clone()]));
+      }
+      start.elapsed()
+    })
+    .min()
+    .unwrap();
+  let nanos = best.as_nanos() as f64 / spans.len() as f64;
+  println!("{name:<28} {nanos:>6.1} ns/token");
+}
+
+fn main() {
+  let (source, spans) = source();
+  println!(
+    "{} tokens, thread cache {}",
+    spans.len(),
+    if cfg!(feature = "thread-cache") {
+      "on"
+    } else {
+      "off"
+    }
+  );
+
+  let interner = Interner::new();
+  bench("Interner::intern", &source, &spans, |s| {
+    black_box(interner.intern(s));
+  });
+  bench("intern", &source, &spans, |s| {
+    black_box(intern(s));
+  });
+  bench("get_interned", &source, &spans, |s| {
+    black_box(get_interned(s));
+  });
+}
@@ -0,0 +1,116 @@
+//! A `const fn` port of [`wyhash::wyhash`], so that the hash of a string
+//! literal can be computed at compile time
+//!
+//! This must produce exactly the same values as the `wyhash` crate, since
+//! the hashes are used to look strings up in the Interner.
+
+const P0: u64 = 0xa076_1d64_78bd_642f;
+const P1: u64 = 0xe703_7ed1_a0b4_28db;
+const P2: u64 = 0x8ebc_6af0_9c88_c6e3;
+const P3: u64 = 0x5899_65cc_7537_4cc3;
+const P4: u64 = 0x1d8e_4e27_c47d_124f;
+const P5: u64 = 0xeb44_acca_b455_d165;
+
+#[inline]
+const fn wymum(a: u64, b: u64) -> u64 {
+  let r = (a as u128) * (b as u128);
+  ((r >> 64) ^ r) as u64
+}
+
+#[inline]
+const fn read64(data: &[u8], i: usize) -> u64 {
+  (data[i + 7] as u64) << 56
+    | (data[i + 6] as u64) << 48
+    | (data[i + 5] as u64) << 40
+    | (data[i + 4] as u64) << 32
+    | (data[i + 3] as u64) << 24
+    | (data[i + 2] as u64) << 16
+    | (data[i + 1] as u64) << 8
+    | (data[i] as u64)
+}
+
+#[inline]
+const fn read32(data: &[u8], i: usize) -> u64 {
+  (data[i + 3] as u64) << 24
+    | (data[i + 2] as u64) << 16
+    | (data[i + 1] as u64) << 8
+    | (data[i] as u64)
+}
+
+#[inline]
+const fn read64_swapped(data: &[u8], i: usize) -> u64 {
+  (read32(data, i) << 32) | read32(data, i + 4)
+}
+
+/// reads the remaining `data.len() - i` bytes (between 1 and 8)
+#[inline]
+const fn read_rest(data: &[u8], i: usize) -> u64 {
+  match data.len() - i {
+    1 => data[i] as u64,
+    2 => (data[i + 1] as u64) << 8 | (data[i] as u64),
+    3 => {
+      (data[i + 1] as u64) << 16 | (data[i] as u64) << 8 | (data[i + 2] as u64)
+    },
+    4 => read32(data, i),
+    5 => read32(data, i) << 8 | (data[i + 4] as u64),
+    6 => {
+      read32(data, i) << 16 | (data[i + 5] as u64) << 8 | (data[i + 4] as u64)
+    },
+    7 => {
+      read32(data, i) << 24
+        | (data[i + 5] as u64) << 16
+        | (data[i + 4] as u64) << 8
+        | (data[i + 6] as u64)
+    },
+    8 => read64_swapped(data, i),
+    _ => panic!(),
+  }
+}
+
+/// Generate a hash for the input data and seed, identical to
+/// [`wyhash::wyhash`]
+pub const fn wyhash(bytes: &[u8], seed: u64) -> u64 {
+  let mut seed = seed;
+  let chunks = bytes.len() / 32;
+  let mut chunk = 0;
+  while chunk < chunks {
+    let i = chunk * 32;
+    seed = wymum(
+      seed ^ P0,
+      wymum(read64(bytes, i) ^ P1, read64(bytes, i + 8) ^ P2)
+        ^ wymum(read64(bytes, i + 16) ^ P3, read64(bytes, i + 24) ^ P4),
+    );
+    chunk += 1;
+  }
+  seed ^= P0;
+
+  let rest = bytes.len() & 31;
+  if rest != 0 {
+    let start = bytes.len() & !31;
+    seed = match ((bytes.len() - 1) & 31) / 8 {
+      0 => wymum(seed, read_rest(bytes, start) ^ P1),
+      1 => wymum(
+        read64_swapped(bytes, start) ^ seed,
+        read_rest(bytes, start + 8) ^ P2,
+      ),
+      2 => {
+        wymum(
+          read64_swapped(bytes, start) ^ seed,
+          read64_swapped(bytes, start + 8) ^ P2,
+        ) ^ wymum(seed, read_rest(bytes, start + 16) ^ P3)
+      },
+      3 => {
+        wymum(
+          read64_swapped(bytes, start) ^ seed,
+          read64_swapped(bytes, start + 8) ^ P2,
+        ) ^ wymum(
+          read64_swapped(bytes, start + 16) ^ seed,
+          read_rest(bytes, start + 24) ^ P4,
+        )
+      },
+      _ => unreachable!(),
+    };
+  }
+
+  wymum(seed, (bytes.len() as u64) ^ P5)
+}
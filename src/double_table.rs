@@ -0,0 +1,790 @@
+//! The lock-free hash table shared by the Interner & the typed pools
+//!
+//! Readers look values up in `id_map` without taking a lock, while a single
+//! writer (holding its pool's `write_lock`) inserts into a second copy,
+//! `id_map_mut`, and then atomically swaps the two. Before the writer touches
+//! its copy again it waits for any readers still using it to depart, which it
+//! detects with a per-thread epoch counter.
+//!
+//! A reader increments its epoch (making it odd) before it loads `id_map`,
+//! and again (making it even) once it's done. The writer swaps the tables
+//! before it loads the epochs. Both sides separate their store from their
+//! load with a `SeqCst` fence, so either the writer sees the reader's odd
+//! epoch & waits for it to change, or the reader sees the new `id_map`.
+//!
+//! The writer only holds the lock on the list of epochs while it copies the
+//! odd ones, and then waits without it, so the writers of different tables
+//! wait at the same time. The epoch counters are never freed, so that they can
+//! still be checked once the lock is released: a thread that terminates marks
+//! its counter dead, and the next new thread reuses it.
+//!
+//! The writer waits with a bounded exponential backoff, and then parks. Before
+//! parking it adds itself to the waiters, and sets the `EPOCH_PARKED` bit of
+//! each epoch it's waiting on, with a compare-exchange that fails if the epoch
+//! has already changed. A reader's closing increment returns the bit, since
+//! both are read-modify-writes of the same atomic, and the reader then clears
+//! the bit & unparks the waiters.
+
+use {
+  crate::{
+    ext::BoxNonNull,
+    sync::{
+      atomic::{self, AtomicPtr, AtomicUsize, Ordering},
+      const_fn, thread, RawMutex, UnsafeCell,
+    },
+  },
+  ::core::{cell::Cell, ptr},
+  ::hashbrown::HashTable,
+};
+
+/// The epoch counters of every thread that has read from a table.
+///
+/// This is shared by all tables, since a read that starts while the thread is
+/// already reading (from an `Eq` impl, say) is covered by the outer read: the
+/// epoch stays odd until the outermost read is done.
+#[cfg(not(loom))]
+static EPOCHS: Epochs = Epochs::new();
+#[cfg(loom)]
+::loom::lazy_static! {
+  static ref EPOCHS: Epochs = Epochs::new();
+}
+
+#[cfg(not(loom))]
+thread_local! {
+  /// This is an epoch counter for the current thread. It allows the writer to
+  /// reliably wait on outstanding reads from id_map_mut
+  static LOCAL_EPOCH: Cell<LocalEpoch> = const { Cell::new(LocalEpoch::None) };
+}
+#[cfg(loom)]
+::loom::thread_local! {
+  static LOCAL_EPOCH: Cell<LocalEpoch> = Cell::new(LocalEpoch::None);
+}
+#[cfg(not(loom))]
+thread_local! {
+  /// The number of reads the current thread is in the middle of. Only the
+  /// outermost read increments the epoch
+  static READ_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+#[cfg(loom)]
+::loom::thread_local! {
+  static READ_DEPTH: Cell<usize> = Cell::new(0);
+}
+/// Local epoch counter starting value
+const LOCAL_EPOCH_INIT: usize = 2;
+/// Local epoch counter gets assigned this value when the thread terminates,
+/// so that the next new thread reuses the atomic
+const LOCAL_EPOCH_DEAD: usize = 0;
+/// Set in an epoch counter by a writer that's parked waiting for the read to
+/// finish, so that the reader unparks it
+const EPOCH_PARKED: usize = 1 << (usize::BITS - 1);
+
+/// The writer spins `2^step` times for each step of its backoff up to this
+/// one, and then yields
+#[cfg(not(loom))]
+const SPIN_LIMIT: u32 = 6;
+/// The writer parks once its backoff reaches this step
+#[cfg(not(loom))]
+const YIELD_LIMIT: u32 = 10;
+
+#[derive(Debug, Clone)]
+enum LocalEpoch {
+  Some(ptr::NonNull<AtomicUsize>),
+  None,
+}
+
+impl Drop for LocalEpoch {
+  fn drop(&mut self) {
+    if let LocalEpoch::Some(ptr) = self {
+      let epoch = unsafe { ptr.as_ref() };
+      // mark this counter as dead, so that another thread can reuse it
+      // note: `Release`, so that a writer which sees this also sees the end of
+      // the thread's last read
+      epoch.store(LOCAL_EPOCH_DEAD, Ordering::Release)
+    }
+  }
+}
+
+/// The list of epoch counters for each thread
+struct Epochs {
+  /// reading/writing of `list` is protected by this lock
+  lock: RawMutex,
+
+  /// references to epoch counters for each thread. Even counters indicate no
+  /// reads are happening. Odd counters indicate reads map be happening.
+  /// The writer can wait until odd counters increment by at least 1, to be
+  /// sure there are no lingering reads on its copy.
+  ///
+  /// note: the counters are never freed, since a writer checks them after it
+  /// has released `lock`
+  list: UnsafeCell<Vec<ptr::NonNull<AtomicUsize>>>,
+
+  /// reading/writing of `waiters` is protected by this lock, since readers
+  /// unpark the writers without holding `lock`
+  waiter_lock: RawMutex,
+
+  /// the writers that are parked waiting for readers to depart
+  waiters: UnsafeCell<Vec<thread::Thread>>,
+}
+
+// safety: `list` is only accessed while holding `lock`, and `waiters` while
+// holding `waiter_lock`
+unsafe impl Sync for Epochs {}
+
+impl Epochs {
+  const_fn! {
+    const fn new() -> Self {
+      Epochs {
+        lock: RawMutex::new(),
+        list: UnsafeCell::new(Vec::new()),
+        waiter_lock: RawMutex::new(),
+        waiters: UnsafeCell::new(Vec::new()),
+      }
+    }
+  }
+
+  /// Add the current thread to the writers to unpark when a read finishes
+  fn add_waiter(&self) {
+    self.waiter_lock.lock();
+    // safety: we're holding the waiter_lock
+    self
+      .waiters
+      .with_mut(|waiters| unsafe { &mut *waiters }.push(thread::current()));
+    unsafe { self.waiter_lock.unlock() };
+  }
+
+  /// Remove the current thread from the writers to unpark
+  fn remove_waiter(&self) {
+    let id = thread::current().id();
+    self.waiter_lock.lock();
+    // safety: we're holding the waiter_lock
+    self.waiters.with_mut(|waiters| {
+      let waiters = unsafe { &mut *waiters };
+      if let Some(i) = waiters.iter().position(|waiter| waiter.id() == id) {
+        waiters.swap_remove(i);
+      }
+    });
+    unsafe { self.waiter_lock.unlock() };
+  }
+
+  /// Unpark the writers, since one of them is waiting for the current thread's
+  /// read to finish
+  ///
+  /// note: the others check their epochs again, and park again
+  #[cold]
+  fn unpark_waiters(&self) {
+    self.waiter_lock.lock();
+    // safety: we're holding the waiter_lock
+    self.waiters.with(|waiters| {
+      for waiter in unsafe { &*waiters } {
+        waiter.unpark();
+      }
+    });
+    unsafe { self.waiter_lock.unlock() };
+  }
+
+  /// The list of epoch counters
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding `lock`
+  #[allow(clippy::mut_from_ref)]
+  unsafe fn list(&self) -> &mut Vec<ptr::NonNull<AtomicUsize>> {
+    // safety: we're holding the lock
+    self.list.with_mut(|list| unsafe { &mut *list })
+  }
+}
+
+/// A pair of hash tables that can be read locklessly, while a writer holding
+/// an external lock inserts new values
+///
+/// All of the `unsafe` writer methods must only be called while holding the
+/// owning pool's `write_lock`.
+pub(crate) struct DoubleTable<V> {
+  /// freely readable* hashtable of values
+  /// readers must (atomically) increment their epoch before and after reading
+  id_map: AtomicPtr<UnsafeCell<HashTable<V>>>,
+
+  /// The writer's (must have lock) version of the id_map.
+  /// Additionally must wait on readers to depart (using epoch counters)
+  /// atomically swapped with id_map by the writer.
+  id_map_mut: AtomicPtr<UnsafeCell<HashTable<V>>>,
+
+  /// stores a copy of the last values added (which may still need to be
+  /// added to the other map), must have the write_lock to read/write
+  pending_add: UnsafeCell<Vec<V>>,
+}
+
+// safety: the tables are only written by the writer, which holds the owning
+// pool's lock, & readers wait on nothing
+unsafe impl<V: Send + Sync> Sync for DoubleTable<V> {}
+unsafe impl<V: Send> Send for DoubleTable<V> {}
+
+/// How long the writer waited for readers to depart from its table
+#[derive(Default)]
+#[cfg_attr(not(feature = "counters"), allow(dead_code))]
+pub(crate) struct EpochWait {
+  /// the steps of backoff, each spinning or yielding
+  pub(crate) spins: u64,
+  /// the times the writer parked
+  pub(crate) parks: u64,
+}
+
+/// Releases a pool's `write_lock` when dropped, so that a panic (in `T`'s
+/// `Eq` implementation, say) doesn't leave the pool locked
+pub(crate) struct Locked<'a>(pub(crate) &'a RawMutex);
+
+impl Drop for Locked<'_> {
+  #[inline]
+  fn drop(&mut self) {
+    // safety: the lock was acquired when this was created
+    unsafe { self.0.unlock() };
+  }
+}
+
+/// A value missing from a [`DoubleTable`], which the writer can add while it
+/// holds the lock, see [`DoubleTable::vacant`]
+pub(crate) struct Vacant<'a, V> {
+  table: &'a DoubleTable<V>,
+  /// note: dropped last, so that the lock is released after publishing
+  _locked: Locked<'a>,
+}
+
+/// Where the writer found a value that was added while it waited for the lock
+pub(crate) enum Found<V> {
+  /// one of the last values added, which aren't in the writer's table yet
+  Pending(V),
+  /// a value in the writer's table
+  Writer(V),
+}
+
+impl<V: Copy> DoubleTable<V> {
+  const_fn! {
+    pub(crate) const fn new() -> Self {
+      DoubleTable {
+        id_map: AtomicPtr::new(ptr::null_mut()),
+        id_map_mut: AtomicPtr::new(ptr::null_mut()),
+        pending_add: UnsafeCell::new(Vec::new()),
+      }
+    }
+  }
+
+  /// The values added last time, which aren't in the writer's table yet
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`
+  #[allow(clippy::mut_from_ref)]
+  #[inline]
+  unsafe fn pending_add(&self) -> &mut Vec<V> {
+    // safety: we're holding the write_lock
+    self
+      .pending_add
+      .with_mut(|pending_add| unsafe { &mut *pending_add })
+  }
+
+  /// Locklessly read the readable table, which is `None` until the first
+  /// value is added
+  ///
+  /// caveat: not technically lockless if this is the first call to the
+  /// interner for this thread (see `local_epoch_or_init`).
+  #[inline]
+  pub(crate) fn read<R>(
+    &self,
+    f: impl FnOnce(Option<&HashTable<V>>) -> R,
+  ) -> R {
+    /// ends the read, even if `f` panics. The outermost read increments the
+    /// epoch again
+    struct Reading(Option<&'static AtomicUsize>);
+    impl Drop for Reading {
+      #[inline]
+      fn drop(&mut self) {
+        READ_DEPTH.with(|depth| depth.set(depth.get() - 1));
+        if let Some(local_epoch) = self.0 {
+          // note: `AcqRel`, so that if a writer parked waiting for us, we see
+          // it in the waiters
+          let epoch = local_epoch.fetch_add(1, Ordering::AcqRel);
+          if epoch & EPOCH_PARKED != 0 {
+            // note: writers only set the bit of an odd epoch, so nothing else
+            // changes it until our next read
+            local_epoch.fetch_and(!EPOCH_PARKED, Ordering::Relaxed);
+            EPOCHS.unpark_waiters();
+          }
+        }
+      }
+    }
+
+    let outermost = READ_DEPTH.with(|depth| {
+      depth.set(depth.get() + 1);
+      depth.get() == 1
+    });
+    let mut reading = Reading(None);
+    // note: a nested read's epoch is already odd, and stays odd until the
+    // outermost read is done, which is after this one. The outer read's fence
+    // also orders it before this read's load of id_map
+    if outermost {
+      let local_epoch = local_epoch_or_init();
+      local_epoch.fetch_add(1, Ordering::Release);
+      reading.0 = Some(local_epoch);
+      // note: the writer must see our odd epoch, or we must see its new
+      // id_map
+      atomic::fence(Ordering::SeqCst);
+    }
+    let id_map = self.id_map.load(Ordering::Acquire);
+    // safety: the writer won't modify this table until our epoch increments
+    match unsafe { id_map.as_ref() } {
+      Some(id_map) => id_map.with(|id_map| f(Some(unsafe { &*id_map }))),
+      None => f(None),
+    }
+  }
+
+  /// Locklessly find the value with the given hash for which `eq` is true,
+  /// if one exists. Also returns the length of the table searched, for
+  /// [`DoubleTable::find_mut`].
+  #[inline]
+  pub(crate) fn find(
+    &self,
+    hash: u64,
+    eq: impl FnMut(&V) -> bool,
+  ) -> (Option<V>, usize) {
+    self.read(|id_map| match id_map {
+      Some(id_map) => (id_map.find(hash, eq).copied(), id_map.len()),
+      None => (None, 0),
+    })
+  }
+
+  /// Check whether the value was added while the writer was waiting for the
+  /// lock, after a failed [`DoubleTable::find`] that searched a table of
+  /// `read_len` values
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`
+  #[inline]
+  pub(crate) unsafe fn find_mut(
+    &self,
+    hash: u64,
+    mut eq: impl FnMut(&V) -> bool,
+    read_len: usize,
+  ) -> Option<Found<V>> {
+    // safety: we're holding the write_lock
+    let pending_add = unsafe { self.pending_add() };
+    if let Some(&pending) = pending_add.iter().find(|pending| eq(pending)) {
+      return Some(Found::Pending(pending));
+    }
+    // if the id_map_mut differs in length to the id_map we checked earlier
+    // then we may need to re-check it. This can happen if we weren't the
+    // immediate next lock acquirer
+    let id_map_mut = self.id_map_mut.load(Ordering::Acquire);
+    // safety: only the writer modifies id_map_mut
+    let id_map_mut = unsafe { id_map_mut.as_ref() }?;
+    id_map_mut.with(|id_map_mut| {
+      let id_map_mut = unsafe { &*id_map_mut };
+      if id_map_mut.len() + pending_add.len() > read_len {
+        return id_map_mut.find(hash, eq).copied().map(Found::Writer);
+      }
+      None
+    })
+  }
+
+  /// Check whether the value was added while the writer was waiting for the
+  /// lock, like [`DoubleTable::find_mut`], and if it wasn't, wait for the
+  /// readers of the writer's table so that the value can be added. Also
+  /// returns how long the writer waited on them.
+  ///
+  /// This is the write path shared by the Interner & the typed pools, which
+  /// each store the new value in their own pages, and then add it with
+  /// [`Vacant::publish`].
+  ///
+  /// # Safety
+  ///
+  /// - `locked` must hold the `write_lock` of this table
+  ///
+  /// # Panics
+  ///
+  /// Panics if the current thread is reading from any table, see
+  /// [`DoubleTable::writer_table`]
+  #[inline]
+  pub(crate) unsafe fn vacant<'a>(
+    &'a self,
+    locked: Locked<'a>,
+    hash: u64,
+    eq: impl FnMut(&V) -> bool,
+    read_len: usize,
+  ) -> Result<(Vacant<'a, V>, EpochWait), Found<V>> {
+    // safety: the caller holds the write_lock
+    if let Some(found) = unsafe { self.find_mut(hash, eq, read_len) } {
+      return Err(found);
+    }
+    // note: the writer's table is borrowed again by `Vacant::writer_table`
+    let (_, wait) = unsafe { self.writer_table() };
+    let vacant = Vacant {
+      table: self,
+      _locked: locked,
+    };
+    Ok((vacant, wait))
+  }
+
+  /// The writer's table, once any readers still using it have departed. Also
+  /// returns how long the writer waited on them.
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`, and the table
+  ///   must not be used after [`DoubleTable::publish`]
+  ///
+  /// # Panics
+  ///
+  /// Panics if the current thread is reading from any table, since it would
+  /// wait for its own read to finish
+  #[allow(clippy::mut_from_ref)]
+  pub(crate) unsafe fn writer_table(&self) -> (&mut HashTable<V>, EpochWait) {
+    assert!(
+      READ_DEPTH.with(Cell::get) == 0,
+      "can't add a new value to a pool while reading one, such as from an \
+       `Eq` impl"
+    );
+
+    // lazy initialisation of id_map_mut
+    let mut id_map_mut = self.id_map_mut.load(Ordering::Acquire);
+    if id_map_mut.is_null() {
+      id_map_mut = Box::into_raw(Box::new(UnsafeCell::new(HashTable::new())));
+      self.id_map_mut.store(id_map_mut, Ordering::Release);
+    }
+
+    // note: the tables were swapped before this, so either we see a reader's
+    // odd epoch, or the reader sees the new id_map
+    atomic::fence(Ordering::SeqCst);
+
+    // copy the odd epochs, only holding the lock while reading the list, so
+    // that the writers of other tables can wait at the same time
+    EPOCHS.lock.lock();
+    // safety: we're holding the lock, and the epochs are never freed
+    let mut odd_epochs = unsafe { EPOCHS.list() }
+      .iter()
+      .map(|ptr| unsafe { ptr.as_ref() })
+      .map(|epoch| (epoch, epoch.load(Ordering::Acquire) & !EPOCH_PARKED))
+      .filter(|(_, e)| (e % 2) == 1)
+      .collect::<Vec<_>>();
+    unsafe { EPOCHS.lock.unlock() };
+
+    // wait until the odd epochs are no longer odd (i.e. readers are done with
+    // this map)
+    let mut wait = EpochWait::default();
+    #[cfg(not(loom))]
+    let mut step = 0;
+    let mut parked = false;
+    loop {
+      // note: a reader increments its epoch with `Release` once it's done, so
+      // seeing the change means its reads happened before our writes
+      odd_epochs.retain(|&(epoch, old)| {
+        epoch.load(Ordering::Acquire) & !EPOCH_PARKED == old
+      });
+      if odd_epochs.is_empty() {
+        break;
+      }
+      // note: loom explores every spin, so it goes straight to parking
+      #[cfg(not(loom))]
+      if step < YIELD_LIMIT {
+        if step < SPIN_LIMIT {
+          for _ in 0..1 << step {
+            ::core::hint::spin_loop();
+          }
+        } else {
+          thread::yield_now();
+        }
+        step += 1;
+        wait.spins += 1;
+        continue;
+      }
+
+      // ask the readers to unpark us once they're done. If the epoch changed
+      // before we could, then that reader is done already.
+      if !parked {
+        EPOCHS.add_waiter();
+        parked = true;
+      }
+      odd_epochs.retain(|&(epoch, old)| {
+        match epoch.compare_exchange(
+          old,
+          old | EPOCH_PARKED,
+          Ordering::Release,
+          Ordering::Acquire,
+        ) {
+          Ok(_) => true,
+          // note: we, or another writer, set the bit before a spurious unpark
+          Err(actual) => actual == old | EPOCH_PARKED,
+        }
+      });
+      if odd_epochs.is_empty() {
+        break;
+      }
+      // note: this may return spuriously, so the epochs are checked again
+      thread::park();
+      wait.parks += 1;
+    }
+    if parked {
+      EPOCHS.remove_waiter();
+    }
+
+    // safety: we're the writer, and the readers of this table have departed
+    (unsafe { self.id_map_mut() }, wait)
+  }
+
+  /// The writer's table, without waiting for its readers
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`, after
+  ///   [`DoubleTable::writer_table`], and the table mustn't be used once
+  ///   another reference to it is made
+  #[allow(clippy::mut_from_ref)]
+  #[inline]
+  unsafe fn id_map_mut(&self) -> &mut HashTable<V> {
+    let id_map_mut = self.id_map_mut.load(Ordering::Acquire);
+    // safety: the caller upholds the contract
+    unsafe { &*id_map_mut }.with_mut(|id_map_mut| unsafe { &mut *id_map_mut })
+  }
+
+  /// The number of values publishing `new` more values will insert into the
+  /// writer's table
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`
+  #[inline]
+  pub(crate) unsafe fn additional(&self, new: usize) -> usize {
+    // safety: we're holding the write_lock
+    new + unsafe { self.pending_add() }.len()
+  }
+
+  /// Insert a new value into the writer's table, and swap the tables so that
+  /// readers can find it
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`, after
+  ///   [`DoubleTable::writer_table`]
+  #[inline]
+  pub(crate) unsafe fn publish(
+    &self,
+    hash: u64,
+    value: V,
+    hasher: impl Fn(&V) -> u64,
+  ) {
+    // safety: the caller upholds the contract
+    unsafe { self.publish_with(::core::iter::once((hash, value)), hasher) }
+  }
+
+  /// Insert a batch of new values into the writer's table, and swap the
+  /// tables once so that readers can find all of them
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`, after
+  ///   [`DoubleTable::writer_table`]
+  #[inline]
+  pub(crate) unsafe fn publish_many(
+    &self,
+    values: &[V],
+    hasher: impl Fn(&V) -> u64,
+  ) {
+    let values = values.iter().map(|v| (hasher(v), *v));
+    // safety: the caller upholds the contract
+    unsafe { self.publish_with(values, &hasher) }
+  }
+
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`, after
+  ///   [`DoubleTable::writer_table`]
+  unsafe fn publish_with(
+    &self,
+    values: impl ExactSizeIterator<Item = (u64, V)>,
+    hasher: impl Fn(&V) -> u64,
+  ) {
+    let id_map_mut_ptr = self.id_map_mut.load(Ordering::Acquire);
+    // safety: we're the writer, and `writer_table` waited for the readers
+    let id_map_mut = unsafe { self.id_map_mut() };
+    // safety: we're holding the write_lock
+    let pending_add = unsafe { self.pending_add() };
+
+    // add the values from last time to this map
+    for pending in pending_add.drain(..) {
+      id_map_mut.insert_unique(hasher(&pending), pending, &hasher);
+    }
+    // note: a large batch shouldn't leave a large buffer behind
+    pending_add.shrink_to(values.len());
+
+    // cache copies for the back buffer table
+    // we defer them until next time to avoid waiting on the observers
+    for (hash, value) in values {
+      id_map_mut.insert_unique(hash, value, &hasher);
+      pending_add.push(value);
+    }
+
+    // swap the tables
+    let id_map = self.id_map.swap(id_map_mut_ptr, Ordering::AcqRel);
+    self.id_map_mut.swap(id_map, Ordering::Release);
+    // note: orders the swap before the next writer's loads of the epochs,
+    // even if that writer is another thread
+    atomic::fence(Ordering::SeqCst);
+  }
+
+  /// Initialise both tables with the values in `id_map`
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`, or with exclusive
+  ///   access, before any values have been added
+  #[cfg(feature = "mmap")]
+  pub(crate) unsafe fn init(&self, id_map: HashTable<V>) {
+    // note: the tables are only initialised when the first value is added,
+    // so there can't be any readers of them yet
+    debug_assert!(self.id_map.load(Ordering::Acquire).is_null());
+    debug_assert!(self.id_map_mut.load(Ordering::Acquire).is_null());
+    let id_map_mut = Box::into_raw(Box::new(UnsafeCell::new(id_map.clone())));
+    self.id_map_mut.store(id_map_mut, Ordering::Release);
+    let id_map = Box::into_raw(Box::new(UnsafeCell::new(id_map)));
+    self.id_map.store(id_map, Ordering::Release);
+  }
+
+  /// The capacities of the readable & the writer's tables
+  ///
+  /// note: this must only be called while holding the `write_lock`, since
+  /// the tables are only resized by the writer
+  pub(crate) fn capacities(&self) -> [usize; 2] {
+    [self.readable_capacity(), Self::capacity(&self.id_map_mut)]
+  }
+
+  /// The capacity of the readable table
+  ///
+  /// note: unlike [`DoubleTable::capacities`] this may be called while the
+  /// writer holds a reference to its table
+  pub(crate) fn readable_capacity(&self) -> usize {
+    Self::capacity(&self.id_map)
+  }
+
+  /// The number of values published to readers
+  ///
+  /// note: this must only be called while holding the `write_lock`
+  pub(crate) fn len(&self) -> usize {
+    let id_map = self.id_map.load(Ordering::Acquire);
+    // safety: the tables are only modified by the writer
+    unsafe { id_map.as_ref() }
+      .map_or(0, |id_map| id_map.with(|id_map| unsafe { &*id_map }.len()))
+  }
+
+  /// note: this must only be called while holding the `write_lock`
+  fn capacity(id_map: &AtomicPtr<UnsafeCell<HashTable<V>>>) -> usize {
+    let id_map = id_map.load(Ordering::Acquire);
+    // safety: the tables are only resized by the writer
+    unsafe { id_map.as_ref() }.map_or(0, |id_map| {
+      id_map.with(|id_map| unsafe { &*id_map }.capacity())
+    })
+  }
+}
+
+impl<V> Drop for DoubleTable<V> {
+  fn drop(&mut self) {
+    // safety: we have exclusive access, so there can be no outstanding readers
+    for id_map in [&self.id_map, &self.id_map_mut] {
+      let id_map = id_map.load(Ordering::Acquire);
+      if !id_map.is_null() {
+        let _ = unsafe { Box::from_raw(id_map) };
+      }
+    }
+  }
+}
+
+impl<V: Copy> Vacant<'_, V> {
+  /// The writer's table, and the number of values that will be added to it
+  /// when the new value is published
+  #[inline]
+  pub(crate) fn writer_table(&mut self) -> (&mut HashTable<V>, usize) {
+    // safety: we're holding the write_lock, `DoubleTable::vacant` waited for
+    // the readers of the writer's table, and the `&mut self` borrow ends
+    // before `Vacant::publish` borrows the table again
+    unsafe { (self.table.id_map_mut(), self.table.additional(1)) }
+  }
+
+  /// Add the new value to the writer's table, swap the tables so that
+  /// readers can find it, and release the lock
+  #[inline]
+  pub(crate) fn publish(
+    self,
+    hash: u64,
+    value: V,
+    hasher: impl Fn(&V) -> u64,
+  ) {
+    // safety: we're holding the write_lock, and `DoubleTable::vacant` waited
+    // for the readers of the writer's table
+    unsafe { self.table.publish(hash, value, hasher) };
+  }
+}
+
+/// Approximately how many bytes a `HashTable<V>` with the given capacity has
+/// allocated
+#[inline]
+pub(crate) fn table_bytes<V>(capacity: usize) -> usize {
+  // note: this mirrors the bucket sizing in hashbrown
+  let buckets = match capacity {
+    0 => return 0,
+    1..4 => 4,
+    4..8 => 8,
+    _ => (capacity * 8 / 7).next_power_of_two(),
+  };
+  // note: each bucket has a 1 byte control byte
+  buckets * (::core::mem::size_of::<V>() + 1)
+}
+
+/// local thread initialisation
+#[inline]
+fn local_epoch_or_init() -> &'static AtomicUsize {
+  LOCAL_EPOCH.with(|cell| {
+    // Need to get a reference to the value in the cell, but it's not Copy
+    // because we want the destructor to run when the thread terminates.
+    if let &LocalEpoch::Some(ptr) = unsafe { &*cell.as_ptr() } {
+      return unsafe { ptr.as_ref() };
+    }
+
+    EPOCHS.lock.lock();
+    // safety: we're holding the lock
+    let epochs = unsafe { EPOCHS.list() };
+    // reuse the counter of a thread that has terminated, if there is one,
+    // since the counters are never freed
+    let dead = epochs.iter().copied().find(|ptr| {
+      unsafe { ptr.as_ref() }.load(Ordering::Acquire) == LOCAL_EPOCH_DEAD
+    });
+    let ptr = match dead {
+      Some(ptr) => {
+        unsafe { ptr.as_ref() }.store(LOCAL_EPOCH_INIT, Ordering::Relaxed);
+        ptr
+      },
+      None => {
+        let ptr = <Box<_> as BoxNonNull<_>>::into_non_null(Box::new(
+          AtomicUsize::new(LOCAL_EPOCH_INIT),
+        ));
+        epochs.push(ptr);
+        ptr
+      },
+    };
+    unsafe { EPOCHS.lock.unlock() };
+
+    cell.set(LocalEpoch::Some(ptr));
+    unsafe { ptr.as_ref() }
+  })
+}
+
+/// The number of threads with a registered epoch counter
+pub(crate) fn thread_epochs() -> usize {
+  EPOCHS.lock.lock();
+  // safety: we're holding the lock
+  let len = unsafe { EPOCHS.list() }
+    .iter()
+    .filter(|ptr| {
+      unsafe { ptr.as_ref() }.load(Ordering::Acquire) != LOCAL_EPOCH_DEAD
+    })
+    .count();
+  unsafe { EPOCHS.lock.unlock() };
+  len
+}
@@ -0,0 +1,323 @@
+use {
+  crate::{
+    interner::{Interner, THE_INTERNER, WYHASH_SEED},
+    sync::const_fn,
+    IStr, InternError, NulError, Stats,
+  },
+  ::core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    convert::AsRef,
+    ffi::CStr,
+    fmt::{self, Debug, Display},
+    hash::Hash,
+    marker::PhantomData,
+    ops::Deref,
+  },
+  ::wyhash::wyhash,
+};
+
+/// A scoped pool layered on top of the global pool
+///
+/// Strings already in the global pool are found there, and new strings are
+/// stored in the generation's own pages, which are all freed at once when the
+/// generation is dropped. The [`GenStr`]s it hands out borrow from it, so they
+/// can't outlive it.
+///
+/// ```rust
+/// # use sinter::{intern, Generation};
+/// let keyword = intern("fn");
+///
+/// let session = Generation::new();
+/// assert!(session.intern("fn") == keyword);
+/// let local = session.intern("local_variable");
+/// assert!(sinter::get_interned("local_variable").is_none());
+/// drop(session);
+/// ```
+pub struct Generation {
+  pool: Interner,
+}
+
+impl Generation {
+  const_fn! {
+    /// Creates a new, empty generation
+    pub const fn new() -> Self {
+      Generation {
+        pool: Interner::new(),
+      }
+    }
+  }
+
+  /// Find the string in the global pool, or intern it in this generation
+  ///
+  /// This operation may be slow, depending on whether the string has been
+  /// previously interned.
+  ///
+  /// Panics if the memory needed to store a new string can't be allocated,
+  /// see [`Generation::try_intern`].
+  #[inline]
+  pub fn intern(&self, s: &str) -> GenStr<'_> {
+    self.try_intern(s).unwrap_or_else(|e| panic!("{e}"))
+  }
+
+  /// Find the string in the global pool, or intern it in this generation
+  ///
+  /// Unlike [`Generation::intern`], this returns an error instead of
+  /// panicking if the memory needed to store a new string can't be allocated,
+  /// or would exceed the generation's
+  /// [byte budget][Generation::set_byte_budget].
+  #[inline]
+  pub fn try_intern(&self, s: &str) -> Result<GenStr<'_>, InternError> {
+    let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+    if let Some(istr) = THE_INTERNER.get_interned_istr_with_wyhash(s, s_wyhash)
+    {
+      return Ok(GenStr::new(istr));
+    }
+    let istr = self.pool.try_intern_istr_with_wyhash(s, s_wyhash)?;
+    Ok(GenStr::new(istr))
+  }
+
+  /// Locklessly find an extant [`GenStr`] corresponding to the string given,
+  /// in either the global pool or this generation
+  #[inline]
+  pub fn get_interned(&self, s: &str) -> Option<GenStr<'_>> {
+    let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+    THE_INTERNER
+      .get_interned_istr_with_wyhash(s, s_wyhash)
+      .or_else(|| self.pool.get_interned_istr_with_wyhash(s, s_wyhash))
+      .map(GenStr::new)
+  }
+
+  /// Statistics about the generation's own pages & tables
+  ///
+  /// See [`Interner::stats`].
+  #[inline]
+  pub fn stats(&self) -> Stats {
+    self.pool.stats()
+  }
+
+  /// Set a limit on the number of bytes the generation may allocate for its
+  /// own pages & tables
+  ///
+  /// See [`Interner::set_byte_budget`].
+  #[inline]
+  pub fn set_byte_budget(&self, budget: Option<usize>) {
+    self.pool.set_byte_budget(budget);
+  }
+}
+
+/// A string found or interned by a [`Generation`]
+///
+/// A `GenStr` borrows from the `Generation` that created it, so it can't
+/// outlive it.
+#[derive(Copy, Clone)]
+pub struct GenStr<'g> {
+  istr: IStr,
+  _generation: PhantomData<&'g Generation>,
+}
+
+impl GenStr<'_> {
+  /// note: the caller must ensure the `IStr` lives for at least `'g`
+  #[inline]
+  fn new(istr: IStr) -> Self {
+    GenStr {
+      istr,
+      _generation: PhantomData,
+    }
+  }
+}
+
+// # reference types & conversion
+
+impl Deref for GenStr<'_> {
+  type Target = str;
+
+  #[inline]
+  fn deref(&self) -> &str {
+    self.istr.as_str()
+  }
+}
+
+impl AsRef<str> for GenStr<'_> {
+  #[inline]
+  fn as_ref(&self) -> &str {
+    self.istr.as_str()
+  }
+}
+
+impl Borrow<str> for GenStr<'_> {
+  #[inline]
+  fn borrow(&self) -> &str {
+    self.istr.as_str()
+  }
+}
+
+impl<'g> GenStr<'g> {
+  /// get the underlying `&str`
+  #[inline]
+  pub fn as_str(&self) -> &'g str {
+    self.istr.as_str()
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`]
+  ///
+  /// # Panics
+  ///
+  /// Panics if the string contains an interior null byte, see
+  /// [`GenStr::try_as_c_str`].
+  #[inline]
+  #[track_caller]
+  pub fn as_c_str(&self) -> &'g CStr {
+    self.istr.as_c_str()
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`], or an error if the
+  /// string contains an interior null byte
+  #[inline]
+  pub fn try_as_c_str(&self) -> Result<&'g CStr, NulError> {
+    self.istr.try_as_c_str()
+  }
+
+  /// The [wyhash](https://crates.io/crates/wyhash) value of this string
+  ///
+  /// This value is cached next to the string by the interner so this method
+  /// call is free.
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    self.istr.wyhash()
+  }
+
+  /// Intern the string in the global pool, so that it outlives the
+  /// generation
+  ///
+  /// This is free if the string was found in the global pool.
+  #[inline]
+  pub fn to_istr(&self) -> IStr {
+    THE_INTERNER.intern_istr_with_wyhash(self.as_str(), self.wyhash())
+  }
+}
+
+impl<'g> From<GenStr<'g>> for &'g str {
+  #[inline]
+  fn from(g: GenStr<'g>) -> &'g str {
+    g.as_str()
+  }
+}
+
+impl From<GenStr<'_>> for String {
+  #[inline]
+  fn from(g: GenStr<'_>) -> String {
+    g.as_str().to_owned()
+  }
+}
+
+impl From<IStr> for GenStr<'_> {
+  /// An [`IStr`] from the global pool lives forever, so it can be used as a
+  /// `GenStr` of any lifetime
+  #[inline]
+  fn from(istr: IStr) -> Self {
+    GenStr::new(istr)
+  }
+}
+
+impl Display for GenStr<'_> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl Debug for GenStr<'_> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_fmt(format_args!("GenStr(\"{}\")", self.as_str()))
+  }
+}
+
+// # equality
+
+impl PartialEq for GenStr<'_> {
+  /// fast [`GenStr`] comparison
+  ///
+  /// This is usually a pointer equality test. But a string that's added to
+  /// the global pool while it's being interned in the generation could be
+  /// stored in both, so pointers that differ fall back to comparing the
+  /// cached hashes, and only then the strings.
+  #[inline]
+  fn eq(&self, rhs: &GenStr<'_>) -> bool {
+    self.istr == rhs.istr
+      || (self.wyhash() == rhs.wyhash() && self.as_str() == rhs.as_str())
+  }
+}
+
+impl Eq for GenStr<'_> {}
+
+impl PartialEq<IStr> for GenStr<'_> {
+  /// fast comparison, the same as comparing two [`GenStr`]s
+  #[inline]
+  fn eq(&self, rhs: &IStr) -> bool {
+    *self == GenStr::new(*rhs)
+  }
+}
+impl PartialEq<GenStr<'_>> for IStr {
+  /// fast comparison, the same as comparing two [`GenStr`]s
+  #[inline]
+  fn eq(&self, rhs: &GenStr<'_>) -> bool {
+    GenStr::new(*self) == *rhs
+  }
+}
+
+impl PartialOrd for GenStr<'_> {
+  #[inline]
+  fn partial_cmp(&self, rhs: &GenStr<'_>) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl Ord for GenStr<'_> {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn cmp(&self, rhs: &GenStr<'_>) -> Ordering {
+    self.as_str().cmp(rhs.as_str())
+  }
+}
+
+impl PartialEq<&str> for GenStr<'_> {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &&str) -> bool {
+    self.as_str() == *other
+  }
+}
+impl PartialEq<GenStr<'_>> for &str {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &GenStr<'_>) -> bool {
+    *self == other.as_str()
+  }
+}
+
+impl PartialEq<String> for GenStr<'_> {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &String) -> bool {
+    self.as_str() == other
+  }
+}
+impl PartialEq<GenStr<'_>> for String {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &GenStr<'_>) -> bool {
+    self == other.as_str()
+  }
+}
+
+// # hashing
+
+impl Hash for GenStr<'_> {
+  /// This feeds the underlying &str into the hasher
+  #[inline]
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.as_str().hash(state);
+  }
+}
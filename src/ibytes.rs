@@ -0,0 +1,309 @@
+use {
+  crate::{
+    interner::{InternError, LEN_FLAGS, LEN_NOT_UTF8},
+    IStr,
+  },
+  ::core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    convert::AsRef,
+    fmt::{self, Debug},
+    hash::Hash,
+    ops::Deref,
+    ptr::NonNull,
+    slice,
+    str::{self, Utf8Error},
+  },
+};
+
+/// An Interned byte string
+///
+/// `IBytes` is like [`IStr`], but for data that may not be valid utf-8. The
+/// two share the same pool: interning bytes that are valid utf-8 stores them
+/// just once, and the `IBytes` converts to the equivalent [`IStr`] for free
+/// with [`IBytes::to_istr`].
+///
+/// ```rust
+/// # use sinter::{intern, intern_bytes, IBytes};
+/// let bytes: IBytes = intern_bytes(b"\xffbytes");
+/// assert_eq!(bytes.as_bytes(), b"\xffbytes");
+/// assert_eq!(bytes.to_istr(), None);
+///
+/// let utf8 = intern_bytes(b"bytes");
+/// assert!(utf8.to_istr() == Some(intern("bytes")));
+/// ```
+#[derive(Eq, Copy, Clone)]
+pub struct IBytes(pub(super) NonNull<u8>);
+
+// safety: an `IBytes` is an immutable reference to `'static` data
+unsafe impl Send for IBytes {}
+unsafe impl Sync for IBytes {}
+
+/// Intern a new byte string, or return the extant [`IBytes`] if one exists
+///
+/// This operation may be slow, depending on whether the bytes have been
+/// previously interned.
+///
+/// Panics if the memory needed to store a new byte string can't be allocated,
+/// see [`try_intern_bytes`].
+#[inline]
+pub fn intern_bytes(bytes: &[u8]) -> IBytes {
+  crate::interner::THE_INTERNER.intern_ibytes(bytes)
+}
+
+/// Intern a new byte string, or return the extant [`IBytes`] if one exists
+///
+/// Unlike [`intern_bytes`], this returns an error instead of panicking if the
+/// memory needed to store a new byte string can't be allocated, or would
+/// exceed the byte budget set with [`set_byte_budget`][crate::set_byte_budget].
+#[inline]
+pub fn try_intern_bytes(bytes: &[u8]) -> Result<IBytes, InternError> {
+  crate::interner::THE_INTERNER.try_intern_ibytes(bytes)
+}
+
+/// Locklessly find an extant [`IBytes`] corresponding to the bytes given, if
+/// they have been interned
+///
+/// Strings interned with [`intern`][crate::intern] are found too.
+#[inline]
+pub fn get_interned_bytes(bytes: &[u8]) -> Option<IBytes> {
+  crate::interner::THE_INTERNER.get_interned_ibytes(bytes)
+}
+
+impl IBytes {
+  /// Intern a new byte string, or return the extant [`IBytes`] if one exists
+  ///
+  /// See [`intern_bytes`].
+  #[inline]
+  pub fn new(bytes: &[u8]) -> Self {
+    intern_bytes(bytes)
+  }
+}
+
+impl From<&[u8]> for IBytes {
+  /// Intern a new byte string, or return the extant [`IBytes`] if one exists
+  #[inline]
+  fn from(bytes: &[u8]) -> Self {
+    intern_bytes(bytes)
+  }
+}
+
+impl From<Vec<u8>> for IBytes {
+  /// Intern a new byte string, or return the extant [`IBytes`] if one exists
+  #[inline]
+  fn from(bytes: Vec<u8>) -> Self {
+    intern_bytes(&bytes)
+  }
+}
+
+impl From<IStr> for IBytes {
+  /// Free conversion, an [`IStr`] is stored the same way as an `IBytes`
+  #[inline]
+  fn from(istr: IStr) -> Self {
+    IBytes(istr.0)
+  }
+}
+
+impl TryFrom<IBytes> for IStr {
+  type Error = Utf8Error;
+
+  /// Cheap conversion, see [`IBytes::to_istr`]
+  #[inline]
+  fn try_from(bytes: IBytes) -> Result<Self, Self::Error> {
+    match bytes.to_istr() {
+      Some(istr) => Ok(istr),
+      // note: this is only slow when the bytes aren't valid utf-8
+      None => Err(str::from_utf8(bytes.as_bytes()).unwrap_err()),
+    }
+  }
+}
+
+// # reference types & conversion
+
+impl Deref for IBytes {
+  type Target = [u8];
+
+  #[inline]
+  fn deref(&self) -> &[u8] {
+    self.as_bytes()
+  }
+}
+
+impl AsRef<[u8]> for IBytes {
+  #[inline]
+  fn as_ref(&self) -> &[u8] {
+    self.as_bytes()
+  }
+}
+
+impl Borrow<[u8]> for IBytes {
+  #[inline]
+  fn borrow(&self) -> &[u8] {
+    self.as_bytes()
+  }
+}
+
+impl IBytes {
+  /// get the underlying `&[u8]`
+  #[inline]
+  pub fn as_bytes(&self) -> &'static [u8] {
+    // safety: the Interner stores the length of the bytes in the header
+    unsafe { slice::from_raw_parts(self.0.as_ptr(), self.header_len()) }
+  }
+
+  /// get the underlying bytes, including the trailing null byte
+  #[inline]
+  pub fn as_bytes_with_nul(&self) -> &'static [u8] {
+    // safety: The Interner always leaves a trailing null byte
+    unsafe { slice::from_raw_parts(self.0.as_ptr(), self.header_len() + 1) }
+  }
+
+  /// Returns `true` if the bytes are valid utf-8
+  ///
+  /// The Interner checks the bytes once when they're interned, and stores the
+  /// result next to them, so this method call is free.
+  #[inline]
+  pub fn is_utf8(&self) -> bool {
+    self.header_len_word() & LEN_NOT_UTF8 == 0
+  }
+
+  /// Cheaply convert to the equivalent [`IStr`], if the bytes are valid utf-8
+  #[inline]
+  pub fn to_istr(self) -> Option<IStr> {
+    // note: an `IStr` has the same representation
+    self.is_utf8().then_some(IStr(self.0))
+  }
+
+  /// The [wyhash](https://crates.io/crates/wyhash) value of these bytes
+  ///
+  /// This value is cached next to the bytes by the interner so this method
+  /// call is free. It's the same as the [`IStr::wyhash`] of the equivalent
+  /// string.
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    IStr(self.0).wyhash()
+  }
+
+  /// The length of the bytes, as stored in the header
+  #[inline]
+  fn header_len(&self) -> usize {
+    (self.header_len_word() & !LEN_FLAGS) as usize
+  }
+
+  /// The length of the bytes, combined with the flags, as stored in the
+  /// header
+  #[inline]
+  fn header_len_word(&self) -> u32 {
+    use crate::interner::{SIZE_OF_LEN, SIZE_OF_WYHASH};
+    // safety: the Interner stores the u32 length of the bytes in the header
+    // preceding the wyhash
+    let len_array: &[u8; SIZE_OF_LEN] = unsafe {
+      let len_ptr = self.0.as_ptr().sub(SIZE_OF_WYHASH + SIZE_OF_LEN);
+      &*(len_ptr as *const [u8; SIZE_OF_LEN])
+    };
+    u32::from_ne_bytes(*len_array)
+  }
+}
+
+impl From<IBytes> for &'static [u8] {
+  #[inline]
+  fn from(b: IBytes) -> &'static [u8] {
+    b.as_bytes()
+  }
+}
+
+impl From<IBytes> for Vec<u8> {
+  #[inline]
+  fn from(b: IBytes) -> Vec<u8> {
+    b.as_bytes().to_owned()
+  }
+}
+
+impl Debug for IBytes {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "IBytes(b\"{}\")", self.as_bytes().escape_ascii())
+  }
+}
+
+// # equality
+
+impl PartialEq for IBytes {
+  /// fast [`IBytes`] comparison (pointer equality test)
+  #[inline]
+  fn eq(&self, rhs: &IBytes) -> bool {
+    // it is sufficient to compare the pointers, because the Interner never
+    // produces two distinct [`IBytes`]s with the same data
+    self.0 == rhs.0
+  }
+}
+
+impl PartialOrd for IBytes {
+  #[inline]
+  fn partial_cmp(&self, rhs: &IBytes) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl Ord for IBytes {
+  /// full (potentially slow) byte comparison
+  #[inline]
+  fn cmp(&self, rhs: &IBytes) -> Ordering {
+    self.as_bytes().cmp(rhs.as_bytes())
+  }
+}
+
+impl PartialEq<&[u8]> for IBytes {
+  /// full (potentially slow) byte comparison
+  #[inline]
+  fn eq(&self, other: &&[u8]) -> bool {
+    self.as_bytes() == *other
+  }
+}
+impl PartialEq<IBytes> for &[u8] {
+  /// full (potentially slow) byte comparison
+  #[inline]
+  fn eq(&self, other: &IBytes) -> bool {
+    *self == other.as_bytes()
+  }
+}
+
+impl<const N: usize> PartialEq<&[u8; N]> for IBytes {
+  /// full (potentially slow) byte comparison
+  #[inline]
+  fn eq(&self, other: &&[u8; N]) -> bool {
+    self.as_bytes() == *other
+  }
+}
+impl<const N: usize> PartialEq<IBytes> for &[u8; N] {
+  /// full (potentially slow) byte comparison
+  #[inline]
+  fn eq(&self, other: &IBytes) -> bool {
+    *self == other.as_bytes()
+  }
+}
+
+impl PartialEq<Vec<u8>> for IBytes {
+  /// full (potentially slow) byte comparison
+  #[inline]
+  fn eq(&self, other: &Vec<u8>) -> bool {
+    self.as_bytes() == other
+  }
+}
+impl PartialEq<IBytes> for Vec<u8> {
+  /// full (potentially slow) byte comparison
+  #[inline]
+  fn eq(&self, other: &IBytes) -> bool {
+    self == other.as_bytes()
+  }
+}
+
+// # hashing
+
+impl Hash for IBytes {
+  /// This feeds the underlying &[u8] into the hasher
+  #[inline]
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.as_bytes().hash(state);
+  }
+}
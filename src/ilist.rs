@@ -0,0 +1,486 @@
+use {
+  crate::{
+    double_table::{DoubleTable, Found, Locked},
+    interned::{global_pool, wyhash_of},
+    sync::{const_fn, RawMutex},
+  },
+  ::core::{
+    cell::Cell,
+    cmp::Ordering,
+    convert::AsRef,
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    iter::FromIterator,
+    ops::Deref,
+    ptr::{self, NonNull},
+    slice,
+    sync::atomic::{self, AtomicPtr, AtomicUsize},
+  },
+  ::std::alloc::{self, Layout},
+};
+
+/// An interned, immutable list of interned handles
+///
+/// `IList<T>` is for sequences that are compared often, such as paths like
+/// `std::collections::HashMap` represented as a list of [`IStr`][crate::IStr]s.
+/// The elements are stored contiguously next to the list's wyhash, so an
+/// `IList` is a single pointer which derefs to a `&'static [T]`. Comparing
+/// two `IList`s is a pointer comparison.
+///
+/// Each element type has its own global [`IListPool`], found with
+/// [`IListPool::of`].
+///
+/// ```rust
+/// # use sinter::{intern, IList, IStr};
+/// let path: IList<IStr> = ["std", "collections", "HashMap"]
+///   .into_iter()
+///   .map(intern)
+///   .collect();
+/// let module: IList<IStr> = [intern("std"), intern("collections")].into();
+/// assert!(path.parent() == Some(module));
+/// assert_eq!(path.last(), Some(&intern("HashMap")));
+/// ```
+pub struct IList<T: 'static>(NonNull<Header<T>>);
+
+// safety: an `IList<T>` is an immutable reference to a `'static` `[T]`
+unsafe impl<T: Sync> Send for IList<T> {}
+unsafe impl<T: Sync> Sync for IList<T> {}
+
+/// The header of each list, which is followed by its elements
+#[repr(C)]
+struct Header<T: 'static> {
+  wyhash: u64,
+  len: usize,
+
+  /// the pool the list was interned in
+  pool: &'static IListPool<T>,
+
+  /// the list without its last element, or null if it hasn't been found yet
+  parent: AtomicPtr<Header<T>>,
+
+  /// aligns the elements which follow the header
+  elements: [T; 0],
+}
+
+/// A pool of interned lists of `T`s
+///
+/// Like the [`InternPool`][crate::InternPool], a pool can be read without
+/// taking a lock, and new lists are appended to pages of memory which are
+/// never freed or moved. A pool can only be used through a `'static`
+/// reference.
+///
+/// ```rust
+/// # use sinter::{IList, IListPool};
+/// static ROUTES: IListPool<u16> = IListPool::new();
+///
+/// let a: IList<u16> = ROUTES.intern(&[80, 443]);
+/// assert!(ROUTES.intern(&[80, 443]) == a);
+/// assert!(ROUTES.get_interned(&[80]).is_none());
+/// ```
+pub struct IListPool<T: 'static> {
+  /// freely readable* hashtable of lists to unique `IList<T>`s
+  /// the writer must hold the write_lock
+  table: DoubleTable<IList<T>>,
+
+  /// reading/writing of all following fields is protected by this lock
+  write_lock: RawMutex,
+
+  /// the page currently being filled
+  page: Cell<*mut u8>,
+
+  /// the number of bytes used on the current page
+  page_len: Cell<usize>,
+
+  /// the number of bytes the current page has room for
+  page_capacity: Cell<usize>,
+
+  /// the number of lists interned
+  len: AtomicUsize,
+}
+
+// safety: memory safety is maintained in a multithreaded context using the
+// `write_lock` and other atomics. Elements are sent to the pool by the thread
+// interning them, and shared with every other thread.
+unsafe impl<T: Send + Sync> Sync for IListPool<T> {}
+unsafe impl<T: Send + Sync> Send for IListPool<T> {}
+
+impl<T: Copy + Hash + Eq + Send + Sync + 'static> IListPool<T> {
+  /// The number of bytes on the first page
+  const PAGE_0_SIZE: usize = 1024;
+
+  const_fn! {
+    /// Creates a new, empty pool
+    pub const fn new() -> Self {
+      IListPool {
+        table: DoubleTable::new(),
+        write_lock: RawMutex::new(),
+        page: Cell::new(ptr::null_mut()),
+        page_len: Cell::new(0),
+        page_capacity: Cell::new(0),
+        len: AtomicUsize::new(0),
+      }
+    }
+  }
+
+  /// The global pool of lists of `T`s used by [`IList::new`]
+  ///
+  /// The first call for each type takes a lock to create its pool, later
+  /// calls take a shared lock to find it.
+  pub fn of() -> &'static Self {
+    global_pool(IListPool::new)
+  }
+
+  /// Intern a new list, or return the extant [`IList`] if one exists
+  ///
+  /// This operation may be slow, depending on whether the list has been
+  /// previously interned.
+  pub fn intern(&'static self, elements: &[T]) -> IList<T> {
+    let hash = wyhash_of(elements);
+    let (ret, id_map_len) =
+      self.table.find(hash, |l| l.as_slice() == elements);
+    if let Some(ilist) = ret {
+      return ilist;
+    }
+
+    // note: looked up before taking the lock, since it isn't needed to find
+    // the parent, which can be recorded by any thread
+    let parent = match elements {
+      [parent @ .., _] => self.get_interned(parent),
+      [] => None,
+    };
+
+    // didn't find it, so acquire a lock and then actually intern a new list
+    self.write_lock.lock();
+    let locked = Locked(&self.write_lock);
+
+    // check it wasn't just added while we were waiting
+    // safety: we're holding the write_lock
+    let (mut vacant, _) = match unsafe {
+      self.table.vacant(
+        locked,
+        hash,
+        |l| l.wyhash() == hash && l.as_slice() == elements,
+        id_map_len,
+      )
+    } {
+      Ok(vacant) => vacant,
+      Err(Found::Pending(ilist) | Found::Writer(ilist)) => {
+        return ilist;
+      },
+    };
+
+    let (id_map_mut, additional) = vacant.writer_table();
+    id_map_mut.reserve(additional, IList::wyhash);
+
+    // safety: we're holding the write_lock
+    let ilist = unsafe { self.push(hash, elements, parent) };
+    self.len.fetch_add(1, atomic::Ordering::Release);
+    vacant.publish(hash, ilist, IList::wyhash);
+    ilist
+  }
+
+  /// Locklessly find an extant [`IList`] equal to the elements given, if one
+  /// exists
+  #[inline]
+  pub fn get_interned(&'static self, elements: &[T]) -> Option<IList<T>> {
+    let hash = wyhash_of(elements);
+    let (ret, _) = self.table.find(hash, |l| l.as_slice() == elements);
+    ret
+  }
+
+  /// The number of lists interned in this pool
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len.load(atomic::Ordering::Acquire)
+  }
+
+  /// Returns `true` if no lists have been interned in this pool
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// The layout of the record for a list of length `len`
+  #[inline]
+  fn layout(len: usize) -> Layout {
+    let elements = Layout::array::<T>(len).unwrap();
+    let (layout, _) = Layout::new::<Header<T>>().extend(elements).unwrap();
+    layout.pad_to_align()
+  }
+
+  /// Append a list to the current page, allocating a new page if it's full
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`
+  unsafe fn push(
+    &'static self,
+    hash: u64,
+    elements: &[T],
+    parent: Option<IList<T>>,
+  ) -> IList<T> {
+    let layout = Self::layout(elements.len());
+    if self.page_capacity.get() - self.page_len.get() < layout.size() {
+      // note: the old page is left as it is, since its lists live forever
+      let capacity =
+        usize::max(Self::PAGE_0_SIZE, self.page_capacity.get() * 2)
+          .max(layout.size());
+      let page_layout =
+        Layout::from_size_align(capacity, layout.align()).unwrap();
+      // safety: the page is never zero sized, since it's at least as big as a
+      // `Header`
+      let page = unsafe { alloc::alloc(page_layout) };
+      if page.is_null() {
+        alloc::handle_alloc_error(page_layout);
+      }
+      self.page.set(page);
+      self.page_len.set(0);
+      self.page_capacity.set(capacity);
+    }
+    // safety: there's room for the record on the page, and every record's size
+    // is padded to the alignment of the header
+    let header =
+      unsafe { self.page.get().add(self.page_len.get()) } as *mut Header<T>;
+    unsafe {
+      header.write(Header {
+        wyhash: hash,
+        len: elements.len(),
+        pool: self,
+        parent: AtomicPtr::new(
+          parent.map_or(ptr::null_mut(), |p| p.0.as_ptr()),
+        ),
+        elements: [],
+      });
+      let ptr = header.add(1) as *mut T;
+      ptr.copy_from_nonoverlapping(elements.as_ptr(), elements.len());
+    }
+    self.page_len.set(self.page_len.get() + layout.size());
+    // safety: the record was just written, and is never moved or freed
+    IList(unsafe { NonNull::new_unchecked(header) })
+  }
+}
+
+impl<T: Copy + Hash + Eq + Send + Sync + 'static> IList<T> {
+  /// Intern a new list in the global pool for `T`, or return the extant
+  /// [`IList`] if one exists
+  ///
+  /// See [`IListPool::intern`].
+  #[inline]
+  pub fn new(elements: &[T]) -> Self {
+    IListPool::of().intern(elements)
+  }
+
+  /// Locklessly find an extant [`IList`] equal to the elements given, if one
+  /// exists in the global pool for `T`
+  ///
+  /// See [`IListPool::get_interned`].
+  #[inline]
+  pub fn get_interned(elements: &[T]) -> Option<Self> {
+    IListPool::of().get_interned(elements)
+  }
+
+  /// The list without its last element, if that list has been interned in
+  /// the same pool
+  ///
+  /// This is free if the parent had been interned before this list was, or
+  /// since the last call. Otherwise it's a lockless lookup, the result of
+  /// which is remembered.
+  #[inline]
+  pub fn parent(&self) -> Option<Self> {
+    let header = self.header();
+    let parent = header.parent.load(atomic::Ordering::Acquire);
+    if let Some(parent) = NonNull::new(parent) {
+      return Some(IList(parent));
+    }
+    let [elements @ .., _] = self.as_slice() else {
+      return None;
+    };
+    let parent = header.pool.get_interned(elements)?;
+    // note: every thread finds the same parent, so it doesn't matter which
+    // store wins
+    header
+      .parent
+      .store(parent.0.as_ptr(), atomic::Ordering::Release);
+    Some(parent)
+  }
+}
+
+impl<T> IList<T> {
+  #[inline]
+  fn header(&self) -> &'static Header<T> {
+    // safety: the record is never moved or freed
+    unsafe { &*self.0.as_ptr() }
+  }
+
+  /// get the underlying `&[T]`
+  #[inline]
+  pub fn as_slice(&self) -> &'static [T] {
+    // safety: the record stores the number of elements, which directly follow
+    // the header, and is never moved or freed
+    unsafe {
+      let ptr = self.0.as_ptr().add(1) as *const T;
+      slice::from_raw_parts(ptr, self.header().len)
+    }
+  }
+
+  /// The wyhash of the list
+  ///
+  /// This value is cached next to the elements by the pool so this method
+  /// call is free.
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    self.header().wyhash
+  }
+}
+
+impl<T> Clone for IList<T> {
+  #[inline]
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for IList<T> {}
+
+// # constructors
+
+impl<T: Copy + Hash + Eq + Send + Sync + 'static> From<&[T]> for IList<T> {
+  #[inline]
+  fn from(elements: &[T]) -> Self {
+    IList::new(elements)
+  }
+}
+
+impl<T: Copy + Hash + Eq + Send + Sync + 'static, const N: usize> From<[T; N]>
+  for IList<T>
+{
+  #[inline]
+  fn from(elements: [T; N]) -> Self {
+    IList::new(&elements)
+  }
+}
+
+impl<T: Copy + Hash + Eq + Send + Sync + 'static> From<Vec<T>> for IList<T> {
+  #[inline]
+  fn from(elements: Vec<T>) -> Self {
+    IList::new(&elements)
+  }
+}
+
+impl<T: Copy + Hash + Eq + Send + Sync + 'static> FromIterator<T>
+  for IList<T>
+{
+  /// Collect the elements, and intern the list in the global pool for `T`
+  #[inline]
+  fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+    IList::new(&iter.into_iter().collect::<Vec<T>>())
+  }
+}
+
+// # reference types & conversion
+
+impl<T> Deref for IList<T> {
+  type Target = [T];
+
+  #[inline]
+  fn deref(&self) -> &[T] {
+    self.as_slice()
+  }
+}
+
+impl<T> AsRef<[T]> for IList<T> {
+  #[inline]
+  fn as_ref(&self) -> &[T] {
+    self.as_slice()
+  }
+}
+
+impl<T> From<IList<T>> for &'static [T] {
+  #[inline]
+  fn from(ilist: IList<T>) -> &'static [T] {
+    ilist.as_slice()
+  }
+}
+
+impl<T> IntoIterator for IList<T> {
+  type Item = &'static T;
+  type IntoIter = slice::Iter<'static, T>;
+
+  #[inline]
+  fn into_iter(self) -> Self::IntoIter {
+    self.as_slice().iter()
+  }
+}
+
+impl<T: Debug> Debug for IList<T> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("IList").field(&self.as_slice()).finish()
+  }
+}
+
+// # equality
+
+impl<T> PartialEq for IList<T> {
+  /// fast [`IList`] comparison (pointer equality test)
+  ///
+  /// note: `IList`s from different pools never compare equal
+  #[inline]
+  fn eq(&self, rhs: &IList<T>) -> bool {
+    // it is sufficient to compare the pointers, because a pool never produces
+    // two distinct [`IList`]s with the same elements
+    self.0 == rhs.0
+  }
+}
+
+impl<T> Eq for IList<T> {}
+
+impl<T: Ord> PartialOrd for IList<T> {
+  #[inline]
+  fn partial_cmp(&self, rhs: &IList<T>) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl<T: Ord> Ord for IList<T> {
+  /// full (potentially slow) comparison of the elements
+  ///
+  /// note: equal lists from different pools are ordered by address, so that
+  /// this is consistent with `Eq`
+  #[inline]
+  fn cmp(&self, rhs: &IList<T>) -> Ordering {
+    if self == rhs {
+      return Ordering::Equal;
+    }
+    self
+      .as_slice()
+      .cmp(rhs.as_slice())
+      .then_with(|| self.0.cmp(&rhs.0))
+  }
+}
+
+impl<T: PartialEq> PartialEq<[T]> for IList<T> {
+  /// full (potentially slow) comparison of the elements
+  #[inline]
+  fn eq(&self, other: &[T]) -> bool {
+    self.as_slice() == other
+  }
+}
+
+impl<T: PartialEq> PartialEq<&[T]> for IList<T> {
+  /// full (potentially slow) comparison of the elements
+  #[inline]
+  fn eq(&self, other: &&[T]) -> bool {
+    self.as_slice() == *other
+  }
+}
+
+// # hashing
+
+impl<T> Hash for IList<T> {
+  /// This feeds the cached wyhash of the list into the hasher
+  #[inline]
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    state.write_u64(self.wyhash());
+  }
+}
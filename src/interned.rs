@@ -0,0 +1,406 @@
+use {
+  crate::{
+    double_table::{DoubleTable, Found, Locked},
+    interner::WYHASH_SEED,
+    sync::{const_fn, RawMutex},
+  },
+  ::core::{
+    any::{Any, TypeId},
+    borrow::Borrow,
+    cell::Cell,
+    cmp::Ordering,
+    convert::AsRef,
+    fmt::{self, Debug, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    ptr::{self, NonNull},
+    sync::atomic::{self, AtomicUsize},
+  },
+  ::hashbrown::HashTable,
+  ::parking_lot::RwLock,
+  ::std::alloc::{self, Layout},
+  ::wyhash::WyHash,
+};
+
+/// The global pools, by the type of the pool
+static POOLS: RwLock<HashTable<(TypeId, &'static (dyn Any + Send + Sync))>> =
+  RwLock::new(HashTable::new());
+
+/// Find the global pool of type `P`, or create it if it doesn't exist yet
+///
+/// The first call for each type takes a lock to create its pool, later calls
+/// take a shared lock to find it.
+pub(crate) fn global_pool<P: Any + Send + Sync>(
+  new: impl FnOnce() -> P,
+) -> &'static P {
+  let type_id = TypeId::of::<P>();
+  let hash = wyhash_of(&type_id);
+  let downcast = |pool: &'static (dyn Any + Send + Sync)| {
+    // note: the pool is only ever stored next to its own `TypeId`
+    pool.downcast_ref::<P>().unwrap()
+  };
+  if let Some(&(_, pool)) = POOLS.read().find(hash, |&(id, _)| id == type_id) {
+    return downcast(pool);
+  }
+  let mut pools = POOLS.write();
+  if let Some(&(_, pool)) = pools.find(hash, |&(id, _)| id == type_id) {
+    return downcast(pool);
+  }
+  let pool: &'static P = Box::leak(Box::new(new()));
+  pools.insert_unique(hash, (type_id, pool), |(id, _)| wyhash_of(id));
+  pool
+}
+
+/// An interned value of any type
+///
+/// `Interned<T>` is like [`IStr`][crate::IStr], but for any `T: Hash + Eq`,
+/// such as syntax tree nodes or tuples of other interned values. Each type has
+/// its own global [`InternPool`], found with [`InternPool::of`]. An `IStr`
+/// isn't an `Interned<str>`: the Interner keeps strings in its own pages, and
+/// only shares the hash table & its write path with the pools.
+///
+/// An `Interned<T>` is a single pointer to the value, which is never freed or
+/// moved. Comparing two `Interned<T>`s is a pointer comparison, and hashing
+/// one feeds the wyhash of the value cached next to it into the hasher.
+///
+/// ```rust
+/// # use sinter::{intern, IStr, Interned};
+/// let a = Interned::new((intern("a"), 1));
+/// let a2 = Interned::new((intern("a"), 1));
+/// let b = Interned::new((intern("b"), 1));
+/// assert!(a == a2);
+/// assert!(a != b);
+/// assert_eq!(a.1, 1);
+/// ```
+pub struct Interned<T: 'static>(NonNull<Record<T>>);
+
+// safety: an `Interned<T>` is an immutable reference to a `'static` `T`
+unsafe impl<T: Sync> Send for Interned<T> {}
+unsafe impl<T: Sync> Sync for Interned<T> {}
+
+/// Each value is stored with its wyhash
+struct Record<T> {
+  wyhash: u64,
+  value: T,
+}
+
+/// A pool of interned values of type `T`
+///
+/// Like the [`Interner`][crate::Interner], a pool can be read without taking
+/// a lock, and new values are appended to pages of memory which are never
+/// freed or moved. Since the values must live forever, a pool can only be used
+/// through a `'static` reference, and the values are never dropped.
+///
+/// Most users will want the type's global pool via [`Interned::new`], but a
+/// pool can also be declared as a `static`, which skips looking up the global
+/// pool:
+///
+/// ```rust
+/// # use sinter::{InternPool, Interned};
+/// static POINTS: InternPool<(i32, i32)> = InternPool::new();
+///
+/// let a: Interned<(i32, i32)> = POINTS.intern((1, 2));
+/// assert!(POINTS.intern((1, 2)) == a);
+/// ```
+pub struct InternPool<T: 'static> {
+  /// freely readable* hashtable of values to unique `Interned<T>`s
+  /// the writer must hold the write_lock
+  table: DoubleTable<Interned<T>>,
+
+  /// reading/writing of all following fields is protected by this lock
+  write_lock: RawMutex,
+
+  /// the page currently being filled
+  page: Cell<*mut Record<T>>,
+
+  /// the number of values on the current page
+  page_len: Cell<usize>,
+
+  /// the number of values the current page has room for
+  page_capacity: Cell<usize>,
+
+  /// the number of values interned
+  len: AtomicUsize,
+}
+
+// safety: memory safety is maintained in a multithreaded context using the
+// `write_lock` and other atomics. Values are sent to the pool by the thread
+// interning them, and shared with every other thread.
+unsafe impl<T: Send + Sync> Sync for InternPool<T> {}
+unsafe impl<T: Send + Sync> Send for InternPool<T> {}
+
+impl<T: Hash + Eq + Send + Sync + 'static> InternPool<T> {
+  /// The number of values on the first page
+  const PAGE_0_LEN: usize = 16;
+
+  const_fn! {
+    /// Creates a new, empty pool
+    pub const fn new() -> Self {
+      InternPool {
+        table: DoubleTable::new(),
+        write_lock: RawMutex::new(),
+        page: Cell::new(ptr::null_mut()),
+        page_len: Cell::new(0),
+        page_capacity: Cell::new(0),
+        len: AtomicUsize::new(0),
+      }
+    }
+  }
+
+  /// The global pool of `T`s used by [`Interned::new`]
+  ///
+  /// The first call for each type takes a lock to create its pool, later
+  /// calls take a shared lock to find it.
+  pub fn of() -> &'static Self {
+    global_pool(InternPool::new)
+  }
+
+  /// Intern a new value, or return the extant [`Interned`] if one exists
+  ///
+  /// This operation may be slow, depending on whether the value has been
+  /// previously interned.
+  ///
+  /// `T`'s `Eq` impl may look up other interned values, but it mustn't intern
+  /// new ones, which panics (or deadlocks, if it's the same pool).
+  pub fn intern(&'static self, value: T) -> Interned<T> {
+    let hash = wyhash_of(&value);
+    let (ret, id_map_len) = self.table.find(hash, |v| **v == value);
+    if let Some(interned) = ret {
+      return interned;
+    }
+
+    // didn't find it, so acquire a lock and then actually intern a new value
+    self.write_lock.lock();
+    let locked = Locked(&self.write_lock);
+
+    // check it wasn't just added while we were waiting
+    // safety: we're holding the write_lock
+    let (mut vacant, _) = match unsafe {
+      self.table.vacant(
+        locked,
+        hash,
+        |v| v.wyhash() == hash && **v == value,
+        id_map_len,
+      )
+    } {
+      Ok(vacant) => vacant,
+      Err(Found::Pending(interned) | Found::Writer(interned)) => {
+        return interned;
+      },
+    };
+
+    let (id_map_mut, additional) = vacant.writer_table();
+    id_map_mut.reserve(additional, Interned::wyhash);
+
+    // safety: we're holding the write_lock
+    let interned = unsafe { self.push(hash, value) };
+    self.len.fetch_add(1, atomic::Ordering::Release);
+    vacant.publish(hash, interned, Interned::wyhash);
+    interned
+  }
+
+  /// Locklessly find an extant [`Interned`] equal to the value given, if one
+  /// exists
+  #[inline]
+  pub fn get_interned<Q>(&'static self, value: &Q) -> Option<Interned<T>>
+  where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    let hash = wyhash_of(value);
+    let (ret, _) = self.table.find(hash, |v| (**v).borrow() == value);
+    ret
+  }
+
+  /// The number of values interned in this pool
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len.load(atomic::Ordering::Acquire)
+  }
+
+  /// Returns `true` if no values have been interned in this pool
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Append a value to the current page, allocating a new page if it's full
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`
+  unsafe fn push(&self, hash: u64, value: T) -> Interned<T> {
+    if self.page_len.get() == self.page_capacity.get() {
+      // note: the old page is left as it is, since its values live forever
+      let capacity =
+        usize::max(Self::PAGE_0_LEN, self.page_capacity.get() * 2);
+      let layout = Layout::array::<Record<T>>(capacity).unwrap();
+      // safety: a `Record` is never zero sized, since it contains a `u64`
+      let page = unsafe { alloc::alloc(layout) } as *mut Record<T>;
+      if page.is_null() {
+        alloc::handle_alloc_error(layout);
+      }
+      self.page.set(page);
+      self.page_len.set(0);
+      self.page_capacity.set(capacity);
+    }
+    // safety: there's room for one more value on the page
+    let record = unsafe { self.page.get().add(self.page_len.get()) };
+    unsafe {
+      record.write(Record {
+        wyhash: hash,
+        value,
+      })
+    };
+    self.page_len.set(self.page_len.get() + 1);
+    // safety: the record was just written, and is never moved or freed
+    Interned(unsafe { NonNull::new_unchecked(record) })
+  }
+}
+
+impl<T: Hash + Eq + Send + Sync + 'static> Interned<T> {
+  /// Intern a new value in the global pool for `T`, or return the extant
+  /// [`Interned`] if one exists
+  ///
+  /// See [`InternPool::intern`].
+  #[inline]
+  pub fn new(value: T) -> Self {
+    InternPool::of().intern(value)
+  }
+
+  /// Locklessly find an extant [`Interned`] equal to the value given, if one
+  /// exists in the global pool for `T`
+  ///
+  /// See [`InternPool::get_interned`].
+  #[inline]
+  pub fn get_interned<Q>(value: &Q) -> Option<Self>
+  where
+    T: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    InternPool::of().get_interned(value)
+  }
+}
+
+impl<T> Interned<T> {
+  /// get the underlying `&T`
+  #[inline]
+  pub fn get(&self) -> &'static T {
+    // safety: the record is never moved or freed
+    unsafe { &(*self.0.as_ptr()).value }
+  }
+
+  /// The wyhash of the value
+  ///
+  /// This value is cached next to the value by the pool so this method call
+  /// is free.
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    // safety: the record is never moved or freed
+    unsafe { (*self.0.as_ptr()).wyhash }
+  }
+}
+
+/// Hash a value with wyhash
+#[inline]
+pub(crate) fn wyhash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+  let mut hasher = WyHash::with_seed(WYHASH_SEED);
+  value.hash(&mut hasher);
+  hasher.finish()
+}
+
+impl<T> Clone for Interned<T> {
+  #[inline]
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for Interned<T> {}
+
+impl<T: Hash + Eq + Send + Sync + 'static> From<T> for Interned<T> {
+  /// Intern a new value, or return the extant [`Interned`] if one exists
+  #[inline]
+  fn from(value: T) -> Self {
+    Interned::new(value)
+  }
+}
+
+// # reference types & conversion
+
+impl<T> Deref for Interned<T> {
+  type Target = T;
+
+  #[inline]
+  fn deref(&self) -> &T {
+    self.get()
+  }
+}
+
+impl<T> AsRef<T> for Interned<T> {
+  #[inline]
+  fn as_ref(&self) -> &T {
+    self.get()
+  }
+}
+
+impl<T: Debug> Debug for Interned<T> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("Interned").field(self.get()).finish()
+  }
+}
+
+impl<T: Display> Display for Interned<T> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    Display::fmt(self.get(), f)
+  }
+}
+
+// # equality
+
+impl<T> PartialEq for Interned<T> {
+  /// fast [`Interned`] comparison (pointer equality test)
+  ///
+  /// note: `Interned`s from different pools never compare equal
+  #[inline]
+  fn eq(&self, rhs: &Interned<T>) -> bool {
+    // it is sufficient to compare the pointers, because a pool never produces
+    // two distinct [`Interned`]s with the same value
+    self.0 == rhs.0
+  }
+}
+
+impl<T> Eq for Interned<T> {}
+
+impl<T: Ord> PartialOrd for Interned<T> {
+  #[inline]
+  fn partial_cmp(&self, rhs: &Interned<T>) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl<T: Ord> Ord for Interned<T> {
+  /// full (potentially slow) comparison of the values
+  ///
+  /// note: equal values from different pools are ordered by address, so that
+  /// this is consistent with `Eq`
+  #[inline]
+  fn cmp(&self, rhs: &Interned<T>) -> Ordering {
+    if self == rhs {
+      return Ordering::Equal;
+    }
+    self.get().cmp(rhs.get()).then_with(|| self.0.cmp(&rhs.0))
+  }
+}
+
+// # hashing
+
+impl<T> Hash for Interned<T> {
+  /// This feeds the cached wyhash of the value into the hasher
+  #[inline]
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    state.write_u64(self.wyhash());
+  }
+}
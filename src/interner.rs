@@ -1,382 +1,1483 @@
+#[cfg(feature = "counters")]
+use crate::stats::{AtomicCounters, Counters};
+#[cfg(feature = "mmap")]
 use {
-  crate::{ext::BoxNonNull as _, IStr},
+  crate::storage::MmapStorage,
+  ::std::{io, path::Path},
+};
+use {
+  crate::{
+    double_table::{table_bytes, thread_epochs, DoubleTable, Found, Locked},
+    stats::{PageStats, Stats},
+    storage::Storage,
+    sync::{const_fn, RawMutex},
+    IBytes, IStr, RegisterError, StaticTable,
+  },
   ::core::{
-    cell::{Cell, OnceCell, UnsafeCell},
+    cell::{Cell, OnceCell},
+    fmt::{self, Display},
     iter,
     mem::MaybeUninit,
+    num::NonZeroU32,
+    ops::Deref,
     ptr,
     sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering},
   },
-  ::hashbrown::HashTable,
-  ::parking_lot::{lock_api::RawMutex as _, RawMutex},
-  ::std::thread,
+  ::hashbrown::{hash_table::Entry, HashTable},
+  ::std::alloc::{self, Layout},
   ::wyhash::wyhash,
 };
 
-// TODO check implementation with loom & miri
-
 /// The String Interner instance singleton
+#[cfg(not(loom))]
 pub(crate) static THE_INTERNER: Interner = Interner::new();
-
-thread_local! {
-  /// This is an epoch counter for the current thread. It allows the writer to
-  /// reliably wait on outstanding reads from id_map_mut
-  static LOCAL_EPOCH: Cell<LocalEpoch> = const { Cell::new(LocalEpoch::None) };
-}
-/// Local epoch counter starting value
-const LOCAL_EPOCH_INIT: usize = 2;
-/// Local epoch counter gets assigned this value when the thread terminates,
-/// effectively transferring ownership of the atomic to the Interner
-const LOCAL_EPOCH_DEAD: usize = 0;
-
-#[derive(Debug, Clone)]
-enum LocalEpoch {
-  Some(ptr::NonNull<AtomicUsize>),
-  None,
+#[cfg(loom)]
+::loom::lazy_static! {
+  pub(crate) static ref THE_INTERNER: Interner = Interner::new();
 }
 
-impl Drop for LocalEpoch {
-  fn drop(&mut self) {
-    if let LocalEpoch::Some(ptr) = self {
-      let epoch = unsafe { ptr.as_ref() };
-      // mark this counter as dead, so that the Interner can clean it its
-      // memory.
-      epoch.store(LOCAL_EPOCH_DEAD, Ordering::Relaxed)
-    }
-  }
+// safety: memory safety is maintained in a multithreaded context using each
+// shard's `write_lock` and other atomics
+unsafe impl Sync for Interner {}
+// safety: the Interner owns all of its pages & tables, and holds no references
+// to thread-local data
+unsafe impl Send for Interner {}
+
+/// A thread-safe string interner
+///
+/// Most users will want the global pool via [`intern`][crate::intern], but an
+/// `Interner` can also be created, used and dropped independently of the
+/// global pool. This is useful for keeping separate pools for separate
+/// sessions, or for isolating tests from one another.
+///
+/// The strings in an `Interner`'s pool are freed when it is dropped, so the
+/// [`PoolStr`][crate::PoolStr] handles it gives out borrow from the
+/// `Interner`.
+///
+/// ```rust
+/// # use sinter::Interner;
+/// let interner = Interner::new();
+/// let a = interner.intern("hello");
+/// let b = interner.intern("hello");
+/// assert_eq!(a, b);
+/// ```
+pub struct Interner {
+  /// the strings, split between independent shards by their wyhash
+  shards: [Shard; SHARDS],
+
+  /// freely readable index of `Sym`s to `IBytes`s, in the order their `Sym`s
+  /// were reserved. Appended to by the writer of any shard
+  syms: SymIndex,
+
+  /// where the memory for the pages comes from
+  storage: Storage,
+
+  /// the maximum number of bytes the pool may allocate, `usize::MAX` for no
+  /// limit
+  byte_budget: AtomicUsize,
+
+  /// instrumentation of the write path
+  #[cfg(feature = "counters")]
+  counters: AtomicCounters,
 }
 
-// safety: memory safety is maintained in a multithreaded context using the
-// `write_lock` and other atomics
-unsafe impl Sync for Interner {}
+/// The number of shards an Interner's strings are split between
+///
+/// Each shard has its own lock, pages & pair of tables, so threads interning
+/// new strings only contend when the strings land in the same shard.
+#[cfg(not(loom))]
+const SHARDS: usize = 16;
+/// note: the loom models only need a couple of shards, and every shard adds
+/// to the states they explore
+#[cfg(loom)]
+const SHARDS: usize = 2;
+
+/// The shard of a string is picked by the bits of its wyhash starting here,
+/// since hashbrown picks buckets with the low bits & tags them with the top 7
+const SHARD_SHIFT: u32 = 48;
+
+/// The index of the shard that owns the string with the given wyhash
+#[inline]
+fn shard_index(s_wyhash: u64) -> usize {
+  (s_wyhash >> SHARD_SHIFT) as usize % SHARDS
+}
 
-/// A thread-safe global string interner
-pub(crate) struct Interner {
-  /// freely readable* hashtable of `&str`s to unique `IStr`s
-  /// readers must (atomically) increment their epoch before and after reading
-  id_map: AtomicPtr<HashTable<IStr>>,
+/// One of the Interner's independent parts, owning the strings whose wyhash
+/// picks it
+struct Shard {
+  /// freely readable* hashtable of `&[u8]`s to unique `IBytes`s
+  /// the writer must hold the write_lock
+  table: DoubleTable<IBytes>,
 
   /// reading/writing of all following fields is protected by this lock
   write_lock: RawMutex,
 
   /// linked list of memory pages, must have write_lock to read/write
-  pages: OnceCell<&'static Page>,
+  pages: OnceCell<PagePtr>,
 
   /// the index of the first unused byte of the last memory page
   last_memory_index: AtomicU32,
 
-  /// The writer's (must have lock) version of the id_map.
-  /// Additionally must wait on readers to depart (using epoch counters)
-  /// atomically swapped with id_map by the writer.
-  id_map_mut: AtomicPtr<HashTable<IStr>>,
+  /// the total length of all the strings interned, must have write_lock to
+  /// read/write
+  payload_bytes: Cell<usize>,
+
+  /// the number of bytes allocated for the pages & tables, as of the last
+  /// allocation checked against the byte budget. Read by the other shards'
+  /// writers for their own checks
+  allocated_bytes: AtomicUsize,
+}
+
+impl Shard {
+  const_fn! {
+    const fn new() -> Self {
+      Shard {
+        table: DoubleTable::new(),
+        write_lock: RawMutex::new(),
+        pages: OnceCell::new(),
+        last_memory_index: AtomicU32::new(0),
+        payload_bytes: Cell::new(0),
+        allocated_bytes: AtomicUsize::new(0),
+      }
+    }
+  }
+
+  /// Acquire the `write_lock`, releasing it when the guard is dropped
+  #[inline]
+  fn lock(&self) -> Locked<'_> {
+    self.write_lock.lock();
+    Locked(&self.write_lock)
+  }
+
+  /// The last page in the deck
+  ///
+  /// note: must only be called while holding the `write_lock`
+  #[inline]
+  fn last_page(&self) -> Option<PagePtr> {
+    let mut last_page = *self.pages.get()?;
+    // safety: `next_page` is initialised while the write_lock is held
+    while let Some(&next_page) =
+      unsafe { last_page.next_page.assume_init_ref() }.get()
+    {
+      last_page = next_page;
+    }
+    Some(last_page)
+  }
+
+  /// The number of bytes allocated for the pages & tables, given the
+  /// capacities of the tables
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the `write_lock`
+  unsafe fn allocated_bytes(&self, table_capacities: [usize; 2]) -> usize {
+    let mut bytes = 0;
+    let mut next_page = self.pages.get();
+    while let Some(page) = next_page {
+      bytes += page.capacity();
+      // safety: `next_page` is initialised while the write_lock is held
+      next_page = unsafe { page.next_page.assume_init_ref() }.get();
+    }
+    let table_bytes: usize = table_capacities
+      .into_iter()
+      .map(table_bytes::<IBytes>)
+      .sum();
+    bytes + table_bytes
+  }
 
-  /// stores a copy of the last `IStr` added (which may still need to be added
-  /// to the other map)
-  pending_add: Cell<Option<IStr>>,
+  /// Free the pages
+  ///
+  /// # Safety
+  ///
+  /// - there must be no outstanding readers, and no `IBytes` borrowing from
+  ///   the pages
+  unsafe fn free(&mut self, storage: &Storage) {
+    let mut next_page = self.pages.take();
+    while let Some(page) = next_page {
+      let page = unsafe { Box::from_raw(page.0.as_ptr()) };
+      next_page = unsafe { page.next_page.assume_init_read() }.take();
+      unsafe { storage.free(page.mem) };
+    }
+  }
+}
 
-  /// references to epoch counters for each thread. Even counters indicate no
-  /// reads are happening. Odd counters indicate reads map be happening.
-  /// The writer can wait until odd counters increment by at least 1, to be
-  /// sure there are no lingering reads on its copy.
-  epochs: UnsafeCell<Vec<(thread::ThreadId, ptr::NonNull<AtomicUsize>)>>,
+/// Increment one of the Interner's counters, if the `counters` feature is
+/// enabled
+macro_rules! count {
+  ($self:ident.$counter:ident) => {
+    count!($self.$counter += 1)
+  };
+  ($self:ident.$counter:ident += $n:expr) => {
+    #[cfg(feature = "counters")]
+    $self
+      .counters
+      .$counter
+      .fetch_add($n as u64, Ordering::Relaxed);
+  };
 }
 
-pub(crate) const WYHASH_SEED: u64 = 0;
+pub const WYHASH_SEED: u64 = 0;
 pub(crate) const SIZE_OF_WYHASH: usize = ::core::mem::size_of::<u64>();
+pub(crate) const SIZE_OF_SYM: usize = ::core::mem::size_of::<u32>();
+pub(crate) const SIZE_OF_LEN: usize = ::core::mem::size_of::<u32>();
+/// The top bit of the length in the header is set if the bytes aren't valid
+/// utf-8
+pub(crate) const LEN_NOT_UTF8: u32 = 1 << 31;
+/// The next bit of the length in the header is set if the bytes contain a
+/// null byte
+pub(crate) const LEN_HAS_NUL: u32 = 1 << 30;
+/// The flag bits of the length in the header
+pub(crate) const LEN_FLAGS: u32 = LEN_NOT_UTF8 | LEN_HAS_NUL;
+/// The top bit of the sym in the header is set for a string of a
+/// `StaticTable`, whose `Sym` is stored in the table instead. The Interner's
+/// own `Sym`s are always below it
+pub(crate) const STATIC_SYM: u32 = 1 << 31;
+/// The longest string the Interner can store
+pub(crate) const MAX_LEN: usize = !LEN_FLAGS as usize;
+/// The size of the header the Interner writes before each string's bytes:
+/// `[sym][len][wyhash]`
+pub(crate) const SIZE_OF_HEADER: usize =
+  SIZE_OF_SYM + SIZE_OF_LEN + SIZE_OF_WYHASH;
+
+/// The largest `Sym` a pool of `strings` strings read from a file may have
+///
+/// note: a crash interrupts at most one write per shard, so there are only
+/// more missing `Sym`s than shards if the pool has crashed repeatedly. As
+/// many as there are strings are allowed, which bounds the `Sym`s reserved
+/// for the gaps by the size of the file
+pub(crate) fn max_sym(strings: usize) -> u32 {
+  let max_sym = strings.saturating_add(usize::max(strings, SHARDS));
+  usize::min(max_sym, STATIC_SYM as usize - 1) as u32
+}
 
 impl Interner {
-  /// Creates a new Interner
-  pub(crate) const fn new() -> Self {
-    Interner {
-      write_lock: RawMutex::INIT,
-      pages: OnceCell::new(),
-      last_memory_index: AtomicU32::new(0),
-      id_map: AtomicPtr::new(ptr::null_mut()),
-      id_map_mut: AtomicPtr::new(ptr::null_mut()),
-      pending_add: Cell::new(None),
-      epochs: UnsafeCell::new(Vec::new()),
+  const_fn! {
+    /// Creates a new Interner with an empty pool
+    pub const fn new() -> Self {
+      Interner::with_storage(Storage::Heap)
+    }
+  }
+
+  const_fn! {
+    const fn with_storage(storage: Storage) -> Self {
+      Interner {
+        #[cfg(not(loom))]
+        shards: [const { Shard::new() }; SHARDS],
+        #[cfg(loom)]
+        shards: ::core::array::from_fn(|_| Shard::new()),
+        syms: SymIndex::new(),
+        storage,
+        byte_budget: AtomicUsize::new(usize::MAX),
+        #[cfg(feature = "counters")]
+        counters: AtomicCounters::new(),
+      }
+    }
+  }
+
+  /// Open a persistent pool stored in the directory at `path`, creating it if
+  /// it doesn't exist
+  ///
+  /// The pool's pages are memory-mapped files, so every string interned is
+  /// written straight to disk. When the pool is opened again the strings are
+  /// all present, with the same [ids][crate::PoolStr::id]. The hash index
+  /// is rebuilt from the wyhash cached alongside each string, so opening a
+  /// pool doesn't re-hash any strings.
+  ///
+  /// Only one `Interner` can have a pool open at a time; opening a pool that
+  /// is already open fails with [`io::ErrorKind::WouldBlock`].
+  ///
+  /// ```rust
+  /// # use sinter::Interner;
+  /// # let dir = ::tempfile::tempdir().unwrap();
+  /// let interner = Interner::open(dir.path()).unwrap();
+  /// let id = interner.intern("persistent").id();
+  /// drop(interner);
+  ///
+  /// let interner = Interner::open(dir.path()).unwrap();
+  /// assert_eq!(interner.resolve(id).unwrap(), "persistent");
+  /// ```
+  #[cfg(feature = "mmap")]
+  pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+    let (storage, pages) = MmapStorage::open(path.as_ref())?;
+    let interner = Interner::with_storage(Storage::Mmap(storage));
+
+    let corrupt =
+      || io::Error::new(io::ErrorKind::InvalidData, "the pool is corrupt");
+    // note: a page holds the strings of any number of shards, so the pages
+    // are all given to the first shard, which carries on writing to the last
+    let first_shard = &interner.shards[0];
+    let mut id_maps: [HashTable<IBytes>; SHARDS] = Default::default();
+    let mut records = Vec::new();
+    let mut last_page = &first_shard.pages;
+    for mem in pages {
+      let page = PagePtr::new(mem);
+      let _ = last_page.set(page);
+      // safety: we have exclusive access to the Interner
+      last_page = unsafe { page.get().next_page.assume_init_ref() };
+
+      // scan the records, a zero sym marks the end of the used area
+      // safety: we have exclusive access to the Interner, so nothing is
+      // writing to the page
+      let read = |i: usize, len: usize| unsafe { page.bytes(i, len) };
+      let read_u32 =
+        |i: usize| u32::from_ne_bytes(read(i, 4).try_into().unwrap());
+      let mut sym_index = 0;
+      while sym_index + SIZE_OF_HEADER < page.capacity() {
+        let Some(sym) = NonZeroU32::new(read_u32(sym_index)) else {
+          break;
+        };
+        let len_index = sym_index + SIZE_OF_SYM;
+        let hash_index = len_index + SIZE_OF_LEN;
+        let str_index = hash_index + SIZE_OF_WYHASH;
+        let len_word = read_u32(len_index);
+        let s_len = (len_word & !LEN_FLAGS) as usize;
+        let s_wyhash = u64::from_ne_bytes(
+          read(hash_index, SIZE_OF_WYHASH).try_into().unwrap(),
+        );
+        if str_index + s_len >= page.capacity() {
+          return Err(corrupt());
+        }
+        let s = read(str_index, s_len);
+        if ::core::str::from_utf8(s).is_ok() != (len_word & LEN_NOT_UTF8 == 0)
+          || s.contains(&0) != (len_word & LEN_HAS_NUL != 0)
+        {
+          return Err(corrupt());
+        }
+        // safety: the record is within the page
+        let ibytes = IBytes(unsafe { page.ptr(str_index) });
+        records.push((sym, ibytes));
+        id_maps[shard_index(s_wyhash)]
+          .insert_unique(s_wyhash, ibytes, |v| v.wyhash());
+        first_shard
+          .payload_bytes
+          .set(first_shard.payload_bytes.get() + s_len);
+        sym_index = str_index + s_len + 1;
+      }
+      page.used.set(sym_index);
+      first_shard
+        .last_memory_index
+        .store(sym_index as u32, Ordering::Relaxed);
     }
+
+    // the shards write their records in whatever order their locks are
+    // taken, so the records are put back in `Sym` order. A `Sym` may be
+    // missing if a crash interrupted a write, but none may be repeated
+    records.sort_unstable_by_key(|&(sym, _)| sym);
+    if records.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+      return Err(corrupt());
+    }
+    if let Some(&(last_sym, _)) = records.last() {
+      if last_sym.get() > max_sym(records.len()) {
+        return Err(corrupt());
+      }
+      interner.syms.reserve_through(last_sym.get());
+      for (sym, ibytes) in records {
+        // safety: the sym was just reserved
+        unsafe { interner.syms.set(sym, ibytes) };
+      }
+    }
+    for (shard, id_map) in interner.shards.iter().zip(id_maps) {
+      if !id_map.is_empty() {
+        // safety: we have exclusive access to the Interner
+        unsafe { shard.table.init(id_map) };
+      }
+    }
+    Ok(interner)
+  }
+
+  /// Flush the pages of a pool opened with [`Interner::open`] to disk
+  ///
+  /// The operating system writes the pages back to disk on its own, so this
+  /// is only needed to make sure the strings survive a system crash. It does
+  /// nothing for a pool that isn't file-backed.
+  #[cfg(feature = "mmap")]
+  pub fn flush(&self) -> io::Result<()> {
+    match &self.storage {
+      Storage::Mmap(mmap) => mmap.flush(),
+      Storage::Heap => Ok(()),
+    }
+  }
+
+  /// Register the strings in a [`StaticTable`] with this pool, so that
+  /// interning any of them returns the string from the table, see
+  /// [`StaticTable::register`]
+  ///
+  /// note: the table's `Sym`s are stored in the table, and resolved through
+  /// the global pool, so this must only be called on the global pool
+  pub(crate) fn register_static_table(
+    &self,
+    table: &'static StaticTable,
+  ) -> Result<(), RegisterError> {
+    // note: the shards are always locked in order, so that this can't
+    // deadlock with another writer holding more than one
+    let _locked: Vec<Locked> = self.shards.iter().map(Shard::lock).collect();
+    if table.is_registered() {
+      return Err(RegisterError::AlreadyRegistered);
+    }
+    let mut new: [HashTable<IBytes>; SHARDS] = Default::default();
+    for istr in table.iter() {
+      let (s, s_wyhash) = (istr.as_bytes(), istr.wyhash());
+      let eq = |v: &IBytes| v.as_bytes() == s;
+      let shard = self.shard(s_wyhash);
+      let (found, read_len) = shard.table.find(s_wyhash, eq);
+      // safety: we're holding the write_lock
+      if found.is_some()
+        || unsafe { shard.table.find_mut(s_wyhash, eq, read_len) }.is_some()
+      {
+        return Err(RegisterError::AlreadyInterned(istr.as_str()));
+      }
+      match new[shard_index(s_wyhash)].entry(s_wyhash, eq, |v| v.wyhash()) {
+        Entry::Occupied(_) => {
+          return Err(RegisterError::Duplicate(istr.as_str()));
+        },
+        Entry::Vacant(entry) => {
+          entry.insert(istr.into());
+        },
+      }
+    }
+    if !table.claim() {
+      return Err(RegisterError::AlreadyRegistered);
+    }
+
+    // the strings are given the next `Sym`s, in the order they're listed
+    for (i, istr) in table.iter().enumerate() {
+      let sym = self.syms.reserve();
+      // safety: the sym was just reserved
+      unsafe { self.syms.set(sym, istr.into()) };
+      table.set_sym(i, sym);
+      let shard = self.shard(istr.wyhash());
+      shard
+        .payload_bytes
+        .set(shard.payload_bytes.get() + istr.len());
+    }
+
+    for (shard, new) in self.shards.iter().zip(new) {
+      if new.is_empty() {
+        continue;
+      }
+      // safety: we're holding the write_lock
+      let (_, _wait) = unsafe { shard.table.writer_table() };
+      count!(self.epoch_wait_spins += _wait.spins);
+      count!(self.epoch_wait_parks += _wait.parks);
+      let new: Vec<IBytes> = new.into_iter().collect();
+      // safety: we're holding the write_lock, and waited for the readers
+      unsafe { shard.table.publish_many(&new, IBytes::wyhash) };
+    }
+
+    Ok(())
+  }
+
+  /// The shard that owns the string with the given wyhash
+  #[inline]
+  fn shard(&self, s_wyhash: u64) -> &Shard {
+    &self.shards[shard_index(s_wyhash)]
+  }
+
+  /// Locklessly find the `IStr` with the given `Sym` number, if one exists
+  /// and is valid utf-8.
+  ///
+  /// note: the `IStr` returned is only valid for as long as `self` is.
+  #[inline]
+  pub(crate) fn resolve_sym(&self, sym: NonZeroU32) -> Option<IStr> {
+    self.syms.get(sym).and_then(IBytes::to_istr)
+  }
+
+  /// Locklessly iterate over the strings & byte strings interned so far, with
+  /// their `Sym` numbers, in the order they were interned
+  ///
+  /// A `Sym` that's reserved but has no string is skipped, since its string
+  /// is still being written, or was lost in a crash.
+  ///
+  /// note: the `IBytes`s are only valid for as long as `self` is.
+  #[inline]
+  pub(crate) fn syms_in_order(
+    &self,
+  ) -> impl Iterator<Item = (NonZeroU32, IBytes)> + '_ {
+    let len = self.syms.len.load(Ordering::Acquire);
+    (1..=len).filter_map(|n| {
+      // safety: n is never zero
+      let sym = unsafe { NonZeroU32::new_unchecked(n) };
+      Some((sym, self.syms.get(sym)?))
+    })
+  }
+
+  /// The number of `Sym`s reserved so far, including any without a string
+  #[inline]
+  pub(crate) fn syms_reserved(&self) -> u32 {
+    self.syms.len.load(Ordering::Acquire)
+  }
+
+  /// Reserve the `Sym`s up to `last` without giving them strings, so that
+  /// the next string interned is given a later one
+  pub(crate) fn skip_syms_through(&self, last: u32) {
+    self.syms.reserve_through(last);
   }
 
   /// Locklessly find an extant `IStr` corresponding to the string given, if
   /// one exists.
-  pub(crate) fn get_interned(&'static self, s: &str) -> Option<IStr> {
+  ///
+  /// note: the `IStr` returned is only valid for as long as `self` is.
+  pub(crate) fn get_interned_istr(&self, s: &str) -> Option<IStr> {
     let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+    self.get_interned_istr_with_wyhash(s, s_wyhash)
+  }
+
+  /// Like `get_interned_istr`, but with the wyhash of `s` already computed
+  #[inline]
+  pub(crate) fn get_interned_istr_with_wyhash(
+    &self,
+    s: &str,
+    s_wyhash: u64,
+  ) -> Option<IStr> {
+    let (ret, _) = self.get_interned_and_map_len(s.as_bytes(), s_wyhash);
+    // safety: the bytes are the same as `s`, so they're valid utf-8
+    ret.map(|ibytes| IStr(ibytes.0))
+  }
+
+  /// Locklessly find an extant `IBytes` corresponding to the bytes given, if
+  /// one exists.
+  ///
+  /// note: the `IBytes` returned is only valid for as long as `self` is.
+  pub(crate) fn get_interned_ibytes(&self, s: &[u8]) -> Option<IBytes> {
+    let s_wyhash = wyhash(s, WYHASH_SEED);
     let (ret, _) = self.get_interned_and_map_len(s, s_wyhash);
     ret
   }
 
   /// Collect all of the currently interned strings into a collection of type
-  /// `B`.
-  pub(crate) fn collect_interned_strings<B>(&'static self) -> B
+  /// `B`. Byte strings that aren't valid utf-8 are skipped.
+  ///
+  /// note: the `IStr`s collected are only valid for as long as `self` is.
+  pub(crate) fn collect_istrs<B>(&self) -> B
   where
     B: iter::FromIterator<IStr>,
   {
-    let local_epoch = self.local_epoch_or_init();
-
-    local_epoch.fetch_add(1, Ordering::Release);
-    let ret = 'reading: {
-      let id_map = self.id_map.load(Ordering::Acquire);
-      if !id_map.is_null() {
-        let id_map = unsafe { &*id_map };
-        break 'reading B::from_iter(id_map.iter().copied());
-      } else {
-        break 'reading B::from_iter(iter::empty());
-      }
-    };
-    local_epoch.fetch_add(1, Ordering::Release);
-
-    ret
+    let mut istrs = Vec::new();
+    for shard in &self.shards {
+      shard.table.read(|id_map| {
+        if let Some(id_map) = id_map {
+          istrs.extend(id_map.iter().copied().filter_map(IBytes::to_istr));
+        }
+      });
+    }
+    B::from_iter(istrs)
   }
 
-  /// locklessly try to get the `IStr` corresponding to the `&str` given, if
-  /// one exists. Also returns the length of the id_map.
+  /// locklessly try to get the `IBytes` corresponding to the `&[u8]` given,
+  /// if one exists. Also returns the length of the id_map.
   ///
   /// caveat: not technically lockless if this is the first call to the
-  /// interner for this thread (see `Interner::local_epoch_or_init`).
+  /// interner for this thread (see `DoubleTable::read`).
   #[inline]
   fn get_interned_and_map_len(
-    &'static self,
+    &self,
+    s: &[u8],
+    s_wyhash: u64,
+  ) -> (Option<IBytes>, usize) {
+    self
+      .shard(s_wyhash)
+      .table
+      .find(s_wyhash, |val| val.as_bytes() == s)
+  }
+
+  /// Intern a new string, or return the extant [`IStr`] if one exists
+  ///
+  /// This operation may be slow, depending on whether the string has been
+  /// previously interned.
+  ///
+  /// note: the `IStr` returned is only valid for as long as `self` is.
+  pub(crate) fn intern_istr(&self, s: &str) -> IStr {
+    let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+    self.intern_istr_with_wyhash(s, s_wyhash)
+  }
+
+  /// Like `intern_istr`, but with the wyhash of `s` already computed
+  ///
+  /// note: `s_wyhash` must be `wyhash(s.as_bytes(), WYHASH_SEED)`
+  #[inline]
+  pub(crate) fn intern_istr_with_wyhash(
+    &self,
     s: &str,
     s_wyhash: u64,
-  ) -> (Option<IStr>, usize) {
-    let local_epoch = self.local_epoch_or_init();
-    let mut id_map_len = 0;
-    // search among the existing Ids in the map
-    local_epoch.fetch_add(1, Ordering::Release);
-    let ret = 'reading: {
-      let id_map = self.id_map.load(Ordering::Acquire);
-      if !id_map.is_null() {
-        let id_map = unsafe { &*id_map };
-        id_map_len = id_map.len();
-        if let Some(&istr) = id_map.find(s_wyhash, |val| val.0 == s) {
-          // we found it!
-          break 'reading Some(istr);
-        }
-      }
-      None
-    };
-    local_epoch.fetch_add(1, Ordering::Release);
-
-    (ret, id_map_len)
-  }
-
-  /// local thread initialisation
-  #[inline]
-  fn local_epoch_or_init(&'static self) -> &AtomicUsize {
-    let local_epoch = LOCAL_EPOCH.with(|cell| {
-      // Need to get a reference to the value in the cell, but it's not Copy
-      // because we want the destructor to run when the thread terminates.
-      if let &LocalEpoch::Some(ptr) = unsafe { &*cell.as_ptr() } {
-        return unsafe { ptr.as_ref() };
-      } else {
-        let ptr =
-          Box::into_non_null(Box::new(AtomicUsize::new(LOCAL_EPOCH_INIT)));
-
-        LOCAL_EPOCH.set(LocalEpoch::Some(ptr));
-        self.write_lock.lock();
-        '_holding_lock: {
-          let epochs = unsafe { &mut *self.epochs.get() };
-
-          // we prune the dead epochs here, because we're holding the
-          // write_lock anyway, and besides we really only need to free them at
-          // all if we're creating a lot of threads and then throwing them
-          // away.
-          // TODO: if this is too slow, we could have another pair of counters.
-          // One to count the number of threads created, and another to count
-          // the number of threads killed. Then we'd only bother to prune if
-          // the difference was greater than the number of epochs in the vec.
-          Self::prune_dead_epochs(epochs);
-
-          epochs.push((thread::current().id(), ptr));
-        }
-        unsafe { self.write_lock.unlock() };
+  ) -> IStr {
+    self
+      .try_intern_istr_with_wyhash(s, s_wyhash)
+      .unwrap_or_else(|e| panic!("{e}"))
+  }
 
-        return unsafe { ptr.as_ref() };
-      }
-    });
+  /// Like `intern_istr`, but returns an error instead of panicking if the
+  /// memory needed can't be allocated
+  ///
+  /// note: the `IStr` returned is only valid for as long as `self` is.
+  #[inline]
+  pub(crate) fn try_intern_istr(&self, s: &str) -> Result<IStr, InternError> {
+    let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+    self.try_intern_istr_with_wyhash(s, s_wyhash)
+  }
 
-    local_epoch
+  /// Like `try_intern_istr`, but with the wyhash of `s` already computed
+  ///
+  /// note: `s_wyhash` must be `wyhash(s.as_bytes(), WYHASH_SEED)`
+  #[inline]
+  pub(crate) fn try_intern_istr_with_wyhash(
+    &self,
+    s: &str,
+    s_wyhash: u64,
+  ) -> Result<IStr, InternError> {
+    let ibytes =
+      self.try_intern_ibytes_with_wyhash(s.as_bytes(), s_wyhash, true)?;
+    // safety: the bytes are the same as `s`, so they're valid utf-8
+    Ok(IStr(ibytes.0))
   }
 
-  /// frees and removes any epoch with a value of `LOCAL_EPOCH_DEAD`
+  /// Intern a new byte string, or return the extant [`IBytes`] if one exists
+  ///
+  /// note: the `IBytes` returned is only valid for as long as `self` is.
   #[inline]
-  fn prune_dead_epochs(
-    epochs: &mut Vec<(thread::ThreadId, ptr::NonNull<AtomicUsize>)>,
-  ) {
-    epochs.retain(|&(_thread_id, ptr)| {
-      let epoch = unsafe { ptr.as_ref() };
-      if epoch.load(Ordering::Acquire) == LOCAL_EPOCH_DEAD {
-        // free the memory for the atomic and remove this entry from the list
-        let _ = unsafe { Box::from_non_null(ptr) };
-        false
-      } else {
-        true
-      }
-    });
+  pub(crate) fn intern_ibytes(&self, s: &[u8]) -> IBytes {
+    self.try_intern_ibytes(s).unwrap_or_else(|e| panic!("{e}"))
   }
 
-  /// Intern a new string, or return the extant [`IStr`] if one exists
+  /// Like `intern_ibytes`, but returns an error instead of panicking if the
+  /// memory needed can't be allocated
   ///
-  /// This operation may be slow, depending on whether the string has been
-  /// previously interned.
-  pub(crate) fn intern(&'static self, s: &str) -> IStr {
-    let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+  /// note: the `IBytes` returned is only valid for as long as `self` is.
+  #[inline]
+  pub(crate) fn try_intern_ibytes(
+    &self,
+    s: &[u8],
+  ) -> Result<IBytes, InternError> {
+    let s_wyhash = wyhash(s, WYHASH_SEED);
+    self.try_intern_ibytes_with_wyhash(s, s_wyhash, false)
+  }
+
+  /// Like `try_intern_ibytes`, but with the wyhash of `s` already computed
+  ///
+  /// `s_is_utf8` may be `true` if `s` is known to be valid utf-8, otherwise
+  /// it's checked if `s` needs to be inserted.
+  ///
+  ///
+  /// note: `s_wyhash` must be `wyhash(s, WYHASH_SEED)`
+  pub(crate) fn try_intern_ibytes_with_wyhash(
+    &self,
+    s: &[u8],
+    s_wyhash: u64,
+    s_is_utf8: bool,
+  ) -> Result<IBytes, InternError> {
+    if s.len() > MAX_LEN {
+      return Err(InternError::TooLong);
+    }
+    debug_assert_eq!(s_wyhash, wyhash(s, WYHASH_SEED));
 
     // see if one already exists
     let (ret, id_map_len) = self.get_interned_and_map_len(s, s_wyhash);
     if let Some(istr) = ret {
-      return istr;
+      count!(self.fast_path_hits);
+      return Ok(istr);
     }
 
-    // didn't find it, so acquire a lock and then actually intern a new string
-    self.write_lock.lock();
-    let ret = 'holding_lock: {
-      let mut id_map_mut = self.id_map_mut.load(Ordering::Acquire);
+    // didn't find it, so acquire the shard's lock and then actually intern a
+    // new string
+    let shard = self.shard(s_wyhash);
+    let locked = self.lock_for_writing(shard);
 
-      // check it wasn't just added while we were waiting
-      // TODO checking this last value is always slow (not really but requires
-      // getting the lock)
-      {
-        let mut some_pending = 0;
-        if let Some(pending_istr) = self.pending_add.get() {
-          some_pending = 1;
-          if pending_istr.wyhash() == s_wyhash && pending_istr.as_str() == s {
-            break 'holding_lock pending_istr;
-          }
-        }
-        // if the id_map_mut differs in length to the id_map we checked earlier
-        // then we may need to re-check it. This can happen if we weren't the
-        // immediate next lock acquirer
-        if !id_map_mut.is_null() {
-          let id_map_mut = unsafe { &*id_map_mut };
-          if id_map_mut.len() + some_pending > id_map_len {
-            if let Some(&istr) = id_map_mut.find(s_wyhash, |val| val.0 == s) {
-              break 'holding_lock istr;
+    // check it wasn't just added while we were waiting
+    // safety: we're holding the write_lock
+    let (mut vacant, _wait) = match unsafe {
+      shard.table.vacant(
+        locked,
+        s_wyhash,
+        |val| val.wyhash() == s_wyhash && val.as_bytes() == s,
+        id_map_len,
+      )
+    } {
+      Ok(vacant) => vacant,
+      Err(Found::Pending(ibytes)) => {
+        count!(self.pending_add_hits);
+        return Ok(ibytes);
+      },
+      Err(Found::Writer(ibytes)) => {
+        count!(self.id_map_mut_hits);
+        return Ok(ibytes);
+      },
+    };
+    count!(self.epoch_wait_spins += _wait.spins);
+    count!(self.epoch_wait_parks += _wait.parks);
+
+    // note: the string is stored in the shard's own pages, rather than an
+    // `InternPool`'s, so that an `IStr` is a thin pointer with its `Sym` in
+    // the header, and the pages can be file-backed
+    let (id_map_mut, additional) = vacant.writer_table();
+    // safety: we're holding the write_lock, and there are no readers of
+    // id_map_mut
+    let interned_str = unsafe {
+      self.try_write_record(
+        shard, id_map_mut, additional, s, s_wyhash, s_is_utf8,
+      )
+    }?;
+
+    // add to id_map, and swap the tables
+    vacant.publish(s_wyhash, interned_str, IBytes::wyhash);
+    count!(self.slow_path_inserts);
+    Ok(interned_str)
+  }
+
+  /// Intern a batch of strings, returning the [`IStr`]s in the same order
+  ///
+  /// note: the `IStr`s returned are only valid for as long as `self` is.
+  pub(crate) fn intern_many_istrs<I>(&self, strs: I) -> Vec<IStr>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    self
+      .try_intern_many_istrs(strs)
+      .unwrap_or_else(|e| panic!("{e}"))
+  }
+
+  /// Like `intern_many_istrs`, but returns an error instead of panicking if a
+  /// string is too long, or the memory needed can't be allocated, see
+  /// `try_intern_many_ibytes_with_wyhash`
+  pub(crate) fn try_intern_many_istrs<I>(
+    &self,
+    strs: I,
+  ) -> Result<Vec<IStr>, InternError>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    let strs: Vec<I::Item> = strs.into_iter().collect();
+    let batch: Vec<(&[u8], u64)> = strs
+      .iter()
+      .map(|s| {
+        let s = s.as_ref().as_bytes();
+        (s, wyhash(s, WYHASH_SEED))
+      })
+      .collect();
+    let ibytes = self.try_intern_many_ibytes_with_wyhash(&batch, true)?;
+    // safety: the bytes are the same as the `str`s, so they're valid utf-8
+    Ok(ibytes.into_iter().map(|b| IStr(b.0)).collect())
+  }
+
+  /// Intern a batch of byte strings with their wyhashes already computed,
+  /// returning the [`IBytes`] in the same order
+  ///
+  /// The strings are looked up with a single lockless read of each shard, and
+  /// any that are missing are interned while taking each shard's
+  /// `write_lock` once, waiting for its readers once, and swapping its tables
+  /// once.
+  ///
+  /// If any of the strings is too long to store, nothing is interned. If the
+  /// memory needed can't be allocated, the strings interned before the
+  /// failure are kept, and the error is returned.
+  ///
+  /// `s_is_utf8` may be `true` if every string is known to be valid utf-8,
+  /// otherwise they're checked if they need to be inserted.
+  ///
+  /// note: each `s_wyhash` must be `wyhash(s, WYHASH_SEED)`
+  pub(crate) fn try_intern_many_ibytes_with_wyhash(
+    &self,
+    batch: &[(&[u8], u64)],
+    s_is_utf8: bool,
+  ) -> Result<Vec<IBytes>, InternError> {
+    if batch.iter().any(|&(s, _)| s.len() > MAX_LEN) {
+      return Err(InternError::TooLong);
+    }
+    debug_assert!(batch.iter().all(|&(s, h)| h == wyhash(s, WYHASH_SEED)));
+
+    // see which already exist, reading each shard once
+    let mut by_shard: [Vec<usize>; SHARDS] = Default::default();
+    for (i, &(_, s_wyhash)) in batch.iter().enumerate() {
+      by_shard[shard_index(s_wyhash)].push(i);
+    }
+    let mut ret = vec![None; batch.len()];
+    let mut id_map_lens = [0; SHARDS];
+    for (shard_i, indices) in by_shard.iter().enumerate() {
+      if indices.is_empty() {
+        continue;
+      }
+      id_map_lens[shard_i] =
+        self.shards[shard_i].table.read(|id_map| match id_map {
+          Some(id_map) => {
+            for &i in indices {
+              let (s, s_wyhash) = batch[i];
+              ret[i] =
+                id_map.find(s_wyhash, |val| val.as_bytes() == s).copied();
             }
-          }
-        }
+            id_map.len()
+          },
+          None => 0,
+        });
+    }
+    count!(self.fast_path_hits += ret.iter().flatten().count());
+
+    // the first index of each distinct missing string, and the indices of
+    // any repeats of them
+    let mut missing: HashTable<usize> = HashTable::new();
+    let mut repeats: Vec<(usize, usize)> = Vec::new();
+    for (i, &(s, s_wyhash)) in batch.iter().enumerate() {
+      if ret[i].is_some() {
+        continue;
       }
+      match missing.entry(
+        s_wyhash,
+        |&first| batch[first].0 == s,
+        |&first| batch[first].1,
+      ) {
+        Entry::Occupied(first) => repeats.push((i, *first.get())),
+        Entry::Vacant(entry) => {
+          entry.insert(i);
+        },
+      }
+    }
+    if missing.is_empty() {
+      return Ok(ret.into_iter().flatten().collect());
+    }
+    let mut missing: Vec<usize> = missing.into_iter().collect();
+    // note: strings are interned in the order they were given, so that their
+    // `Sym`s are too
+    missing.sort_unstable();
 
-      // lazy initialisation of id_map_mut
-      if id_map_mut.is_null() {
-        id_map_mut = Box::into_raw(Box::new(HashTable::new()));
+    // acquire the lock of each shard with missing strings once
+    // note: the shards are always locked in order, so that two batches can't
+    // deadlock
+    let mut needs_lock = [false; SHARDS];
+    for &i in &missing {
+      needs_lock[shard_index(batch[i].1)] = true;
+    }
+    let mut locked: [Option<Locked>; SHARDS] = Default::default();
+    for (shard_i, shard) in self.shards.iter().enumerate() {
+      if needs_lock[shard_i] {
+        locked[shard_i] = Some(self.lock_for_writing(shard));
+      }
+    }
+    // check they weren't just added while we were waiting
+    missing.retain(|&i| {
+      let (s, s_wyhash) = batch[i];
+      let shard_i = shard_index(s_wyhash);
+      // safety: we're holding the write_lock
+      match unsafe {
+        self.shards[shard_i].table.find_mut(
+          s_wyhash,
+          |val| val.wyhash() == s_wyhash && val.as_bytes() == s,
+          id_map_lens[shard_i],
+        )
+      } {
+        Some(Found::Pending(ibytes)) => {
+          count!(self.pending_add_hits);
+          ret[i] = Some(ibytes);
+          false
+        },
+        Some(Found::Writer(ibytes)) => {
+          count!(self.id_map_mut_hits);
+          ret[i] = Some(ibytes);
+          false
+        },
+        None => true,
       }
-      let id_map_mut = unsafe { &mut *id_map_mut };
+    });
 
-      // iterate all odd epochs until they're no longer odd (i.e. readers are
-      // done with this map)
-      {
-        let epochs = unsafe { &mut *self.epochs.get() };
-        let all_epochs = epochs.iter().collect::<Vec<_>>();
-        // TODO remove this clone, cache a vec instead, use smallvec
-        let mut odd_epochs = all_epochs
-          .iter()
-          .enumerate()
-          .map(|(i, (thread_id, ptr_epoch))| {
-            let e = unsafe { ptr_epoch.as_ref() };
-            (i, thread_id, e.load(Ordering::Acquire))
-          })
-          .filter(|(_, _, e)| (e % 2) == 1)
-          .collect::<Vec<_>>();
-        if odd_epochs.is_empty() {
-          let mut spin = 0;
-          loop {
-            odd_epochs.retain(|&(i, _thread_id, old)| {
-              let epoch_i = unsafe { epochs[i].1.as_ref() };
-              let new = epoch_i.load(Ordering::Relaxed);
-              new == old
-            });
-            if odd_epochs.is_empty() {
-              break;
-            }
-            // TODO: improve this spin loop, exponential back-off, waiting on
-            // src threads to signal for this to continue (parking/unparking)
-            ::core::hint::spin_loop();
-            if spin > 100 {
-              thread::yield_now();
-            }
-            spin += 1;
-          }
+    // note: each shard's readers are only waited on once, before the first
+    // string is written to it
+    let mut id_maps_mut: [Option<&mut HashTable<IBytes>>; SHARDS] =
+      Default::default();
+    let mut new: [Vec<IBytes>; SHARDS] = Default::default();
+    let result = 'holding_lock: {
+      for i in missing {
+        let (s, s_wyhash) = batch[i];
+        let shard_i = shard_index(s_wyhash);
+        let shard = &self.shards[shard_i];
+        let id_map_mut = id_maps_mut[shard_i].get_or_insert_with(|| {
+          // safety: we're holding the write_lock
+          let (id_map_mut, _wait) = unsafe { shard.table.writer_table() };
+          count!(self.epoch_wait_spins += _wait.spins);
+          count!(self.epoch_wait_parks += _wait.parks);
+          id_map_mut
+        });
+        // safety: we're holding the write_lock, and there are no readers of
+        // id_map_mut
+        let additional =
+          unsafe { shard.table.additional(new[shard_i].len() + 1) };
+        match unsafe {
+          self.try_write_record(
+            shard, id_map_mut, additional, s, s_wyhash, s_is_utf8,
+          )
+        } {
+          Ok(ibytes) => {
+            ret[i] = Some(ibytes);
+            new[shard_i].push(ibytes);
+          },
+          Err(e) => break 'holding_lock Err(e),
         }
       }
+      Ok(())
+    };
 
-      // add the value from last time to this map
-      if let Some(pending_istr) = self.pending_add.take() {
-        id_map_mut.insert_unique(pending_istr.wyhash(), pending_istr, |v| {
-          wyhash(v.as_bytes(), WYHASH_SEED)
-        });
+    // add everything that was written to each shard's id_map, and swap its
+    // tables, even if a later string failed, since the strings written
+    // already have `Sym`s
+    for ((shard, new), locked) in self.shards.iter().zip(&new).zip(locked) {
+      if !new.is_empty() {
+        // safety: we're holding the write_lock
+        unsafe { shard.table.publish_many(new, IBytes::wyhash) };
+        count!(self.slow_path_inserts += new.len());
       }
+      drop(locked);
+    }
+    result?;
 
-      // write the string to memory page
-      let interned_str;
+    for (i, first) in repeats {
+      ret[i] = ret[first];
+    }
+    Ok(ret.into_iter().flatten().collect())
+  }
+
+  /// Write a new string to the shard's last page (or a new page) and give it
+  /// the next `Sym`, making sure there's room for `additional` more entries
+  /// in `id_map_mut`
+  ///
+  /// The string can't be found until it's published to the shard's table.
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the shard's `write_lock`
+  /// - `id_map_mut` must be the shard's writer's table, with no remaining
+  ///   readers
+  unsafe fn try_write_record(
+    &self,
+    shard: &Shard,
+    id_map_mut: &mut HashTable<IBytes>,
+    additional: usize,
+    s: &[u8],
+    s_wyhash: u64,
+    s_is_utf8: bool,
+  ) -> Result<IBytes, InternError> {
+    debug_assert!(s.len() <= MAX_LEN);
+    let s_is_utf8 = s_is_utf8 || ::core::str::from_utf8(s).is_ok();
+    let mut len_word = s.len() as u32;
+    if !s_is_utf8 {
+      len_word |= LEN_NOT_UTF8;
+    }
+    if s.contains(&0) {
+      len_word |= LEN_HAS_NUL;
+    }
+    // note: we leave room for the header and the trailing null byte
+    let record_len = SIZE_OF_HEADER + s.len() + 1;
+
+    // find out whether the string fits on the last page
+    let last_page = shard.last_page();
+    let new_page_capacity = match last_page {
+      Some(page)
+        if page.capacity()
+          - shard.last_memory_index.load(Ordering::Acquire) as usize
+          >= record_len =>
       {
-        // lazily initialise the first page
-        if self.pages.get().is_none() {
-          // note: we leave room for a trailing null byte
-          let _ = self
-            .pages
-            .set(Page::with_min_capacity(SIZE_OF_WYHASH + s.len() + 1));
-        }
-        // find the last page in the deck
-        let mut last_page = &self.pages;
-        loop {
-          let next_page =
-            unsafe { last_page.get().unwrap().next_page.assume_init_ref() };
-          if next_page.get().is_none() {
-            break;
-          }
-          last_page = next_page;
-        }
-        let mut last_page = *last_page.get().unwrap();
-
-        let available_bytes = unsafe { (*last_page.mem.get()).len() }
-          - self.last_memory_index.load(Ordering::Acquire) as usize;
-        if available_bytes < (SIZE_OF_WYHASH + s.len() + 1) {
-          // we don't have enough memory to store this string, so create a new
-          // page
-          // note: we leave room for the trailing null byte and the wyhash
-          unsafe {
-            last_page.extend_with_new_page(SIZE_OF_WYHASH + s.len() + 1)
-          };
-          let next_page = unsafe { last_page.next_page.assume_init_ref() };
-          last_page = next_page.get().unwrap();
-          self.last_memory_index.store(0, Ordering::Release);
-        }
-        // there's enough bytes available on this page, so store the string
-        let hash_index =
-          self.last_memory_index.load(Ordering::Acquire) as usize;
-        let str_index = hash_index + SIZE_OF_WYHASH;
-        let mem = unsafe { &mut *last_page.mem.get() };
-        let hash_slice = &mut mem[hash_index..(hash_index + SIZE_OF_WYHASH)];
-        hash_slice.copy_from_slice(&s_wyhash.to_ne_bytes());
-        let str_slice = &mut mem[str_index..(str_index + s.len())];
-        str_slice.copy_from_slice(s.as_bytes());
-        // note: we leave room for the trailing null byte
-        self
-          .last_memory_index
-          .store((str_index + s.len() + 1) as u32, Ordering::Release);
-
-        interned_str = IStr(::core::str::from_utf8(str_slice).unwrap());
+        None
+      },
+      // next page should be double the size of the current page (at least)
+      Some(page) => Some(Page::capacity_for(usize::max(
+        page.capacity() * 2,
+        record_len,
+      ))),
+      None => Some(Page::capacity_for(record_len)),
+    };
+
+    // make sure all of the memory we need is available before changing
+    // anything, so that a failure leaves the pool as it was
+    // safety: we're holding the write_lock, and there are no readers of
+    // id_map_mut
+    unsafe {
+      self.try_reserve(
+        shard,
+        id_map_mut,
+        additional,
+        last_page,
+        new_page_capacity,
+      )
+    }?;
+
+    // note: the sym is only reserved once nothing else can fail, so that no
+    // sym is skipped
+    let sym = self.syms.reserve();
+
+    // write the string to memory page
+    let interned_str;
+    {
+      // note: `try_reserve` made sure there's enough bytes available on the
+      // last page
+      let last_page = shard.last_page().unwrap();
+      let sym_index = shard.last_memory_index.load(Ordering::Acquire) as usize;
+      let len_index = sym_index + SIZE_OF_SYM;
+      let hash_index = len_index + SIZE_OF_LEN;
+      let str_index = hash_index + SIZE_OF_WYHASH;
+      // safety: we're holding the write_lock, and the record is past the end
+      // of every string already interned in this shard
+      unsafe {
+        last_page.write(len_index, &len_word.to_ne_bytes());
+        last_page.write(hash_index, &s_wyhash.to_ne_bytes());
+        last_page.write(str_index, s);
+        // note: the null byte is written explicitly, in case a file-backed
+        // page contains a partial record left by a crash
+        last_page.write(str_index + s.len(), &[0]);
+        // note: the sym is written last, so that a file-backed page never
+        // contains a record with a non-zero sym that wasn't fully written
+        last_page.write(sym_index, &sym.get().to_ne_bytes());
       }
+      shard
+        .last_memory_index
+        .store((str_index + s.len() + 1) as u32, Ordering::Release);
+      shard.payload_bytes.set(shard.payload_bytes.get() + s.len());
 
-      // add to id_map
-      id_map_mut.insert_unique(s_wyhash, interned_str, |v| {
-        wyhash(v.as_bytes(), WYHASH_SEED)
-      });
+      // safety: the record is within the page
+      interned_str = IBytes(unsafe { last_page.ptr(str_index) });
+    }
 
-      // cache a copy for the back buffer table
-      // we defer it until next time to avoid waiting on the observers
-      self.pending_add.set(Some(interned_str));
+    // publish the new sym
+    // safety: the sym was just reserved
+    unsafe { self.syms.set(sym, interned_str) };
 
-      // swap the tables
-      let id_map = self.id_map.swap(id_map_mut, Ordering::AcqRel);
-      self.id_map_mut.swap(id_map, Ordering::Release);
+    Ok(interned_str)
+  }
 
-      break 'holding_lock interned_str;
+  /// Acquire the shard's `write_lock`, timing how long it takes if it's
+  /// contended
+  #[inline]
+  fn lock_for_writing<'a>(&self, shard: &'a Shard) -> Locked<'a> {
+    #[cfg(feature = "counters")]
+    if !shard.write_lock.try_lock() {
+      let start = ::std::time::Instant::now();
+      shard.write_lock.lock();
+      count!(self.lock_waits);
+      count!(self.lock_wait_nanos += start.elapsed().as_nanos());
+    }
+    #[cfg(not(feature = "counters"))]
+    shard.write_lock.lock();
+    Locked(&shard.write_lock)
+  }
+
+  /// A snapshot of the counters of the work done by this pool's write path
+  ///
+  /// ```rust
+  /// # use sinter::Interner;
+  /// let interner = Interner::new();
+  /// interner.intern("counters");
+  /// interner.intern("counters");
+  /// let counters = interner.counters();
+  /// assert_eq!(counters.slow_path_inserts, 1);
+  /// assert_eq!(counters.fast_path_hits, 1);
+  /// ```
+  #[cfg(feature = "counters")]
+  #[inline]
+  pub fn counters(&self) -> Counters {
+    self.counters.load()
+  }
+
+  /// Make sure there's enough memory to intern one more string in the shard:
+  /// room for `additional` more entries in `id_map_mut` & one more in `syms`,
+  /// and a new page of `new_page_capacity` bytes if the string doesn't fit on
+  /// the `last_page`.
+  ///
+  /// # Safety
+  ///
+  /// - must only be called while holding the shard's `write_lock`
+  /// - `id_map_mut` must be the shard's writer's table, with no remaining
+  ///   readers
+  unsafe fn try_reserve(
+    &self,
+    shard: &Shard,
+    id_map_mut: &mut HashTable<IBytes>,
+    additional: usize,
+    last_page: Option<PagePtr>,
+    new_page_capacity: Option<usize>,
+  ) -> Result<(), InternError> {
+    let table_capacity = id_map_mut.capacity();
+    let grow_table = table_capacity - id_map_mut.len() < additional;
+    let sym_bucket_bytes = self.syms.bytes_to_reserve();
+
+    let budget = self.byte_budget.load(Ordering::Relaxed);
+    let check_budget = budget != usize::MAX
+      && (new_page_capacity.is_some() || grow_table || sym_bucket_bytes > 0);
+    if check_budget {
+      // note: the writer's table is borrowed, so its capacity is passed in
+      // safety: we're holding the write_lock
+      let shard_bytes = unsafe {
+        shard
+          .allocated_bytes([shard.table.readable_capacity(), table_capacity])
+      };
+      shard.allocated_bytes.store(shard_bytes, Ordering::Relaxed);
+      // note: the other shards' bytes are as of their last allocation
+      let mut bytes = self.syms.allocated_bytes()
+        + self
+          .shards
+          .iter()
+          .map(|shard| shard.allocated_bytes.load(Ordering::Relaxed))
+          .sum::<usize>();
+      bytes += new_page_capacity.unwrap_or(0);
+      if bytes > budget {
+        return Err(InternError::PageAllocation);
+      }
+      if grow_table {
+        // note: the table at least doubles in size when it grows
+        let new_capacity =
+          usize::max(id_map_mut.len() + additional, table_capacity + 1);
+        bytes += table_bytes::<IBytes>(new_capacity)
+          - table_bytes::<IBytes>(table_capacity);
+      }
+      bytes += sym_bucket_bytes;
+      if bytes > budget {
+        return Err(InternError::TableGrowth);
+      }
+    }
+
+    if grow_table {
+      id_map_mut
+        .try_reserve(additional, |v| v.wyhash())
+        .map_err(|_| InternError::TableGrowth)?;
+    }
+    self
+      .syms
+      .try_reserve()
+      .map_err(|_| InternError::TableGrowth)?;
+
+    if let Some(capacity) = new_page_capacity {
+      let page = Page::try_with_capacity(capacity, &self.storage)
+        .ok_or(InternError::PageAllocation)?;
+      match last_page {
+        // safety: `next_page` is initialised while the write_lock is held
+        Some(last_page) => {
+          // note: the rest of the last page is left unused
+          let used = shard.last_memory_index.load(Ordering::Acquire);
+          last_page.used.set(used as usize);
+          let _ = unsafe { last_page.next_page.assume_init_ref() }.set(page);
+        },
+        None => {
+          let _ = shard.pages.set(page);
+        },
+      }
+      shard.last_memory_index.store(0, Ordering::Release);
+    }
+
+    if check_budget {
+      // publish what this shard has allocated for the other shards' checks
+      // safety: we're holding the write_lock
+      let shard_bytes = unsafe {
+        shard.allocated_bytes([
+          shard.table.readable_capacity(),
+          id_map_mut.capacity(),
+        ])
+      };
+      shard.allocated_bytes.store(shard_bytes, Ordering::Relaxed);
+    }
+    Ok(())
+  }
+
+  /// Gather statistics about the pool's size & memory usage
+  ///
+  /// This briefly takes the same locks as interning a new string, one shard
+  /// at a time.
+  ///
+  /// ```rust
+  /// # use sinter::Interner;
+  /// let interner = Interner::new();
+  /// interner.intern("stats");
+  /// let stats = interner.stats();
+  /// assert_eq!(stats.strings, 1);
+  /// assert_eq!(stats.payload_bytes, 5);
+  /// ```
+  pub fn stats(&self) -> Stats {
+    let mut stats = Stats {
+      thread_epochs: thread_epochs(),
+      ..Stats::default()
     };
-    unsafe { self.write_lock.unlock() };
-    ret
+    for (shard_i, shard) in self.shards.iter().enumerate() {
+      let _locked = shard.lock();
+      let first_page = stats.pages.len();
+      let mut next_page = shard.pages.get();
+      while let Some(page) = next_page {
+        // safety: `next_page` is initialised while the write_lock is held
+        next_page = unsafe { page.next_page.assume_init_ref() }.get();
+        let used = match next_page {
+          Some(_) => page.used.get(),
+          None => shard.last_memory_index.load(Ordering::Acquire) as usize,
+        };
+        stats.pages.push(PageStats {
+          shard: shard_i,
+          capacity: page.capacity(),
+          used,
+        });
+      }
+      // note: the last page's tail isn't wasted, it's still available
+      if let Some((_, full_pages)) = stats.pages[first_page..].split_last() {
+        stats.wasted_bytes += full_pages
+          .iter()
+          .map(|page| page.capacity - page.used)
+          .sum::<usize>();
+      }
+      let capacities = shard.table.capacities();
+      stats.id_map_capacity += capacities[0];
+      stats.id_map_mut_capacity += capacities[1];
+      stats.payload_bytes += shard.payload_bytes.get();
+      // note: counted from the tables rather than the `Sym`s, which include
+      // any reserved without a string
+      stats.strings += shard.table.len();
+      // safety: we're holding the write_lock
+      stats.allocated_bytes += unsafe { shard.allocated_bytes(capacities) };
+    }
+    stats.allocated_bytes += self.syms.allocated_bytes();
+    stats
+  }
+
+  /// Set the maximum number of bytes this pool may allocate, or `None` for no
+  /// limit
+  ///
+  /// The pages of strings, the hash tables & the [`Sym`][crate::Sym] index
+  /// all count towards the budget. When interning a new string would need an
+  /// allocation that takes the pool over its budget, [`Interner::try_intern`]
+  /// returns an error instead. Strings that fit in the memory already
+  /// allocated can still be interned after the budget is exceeded.
+  ///
+  /// note: the size of the hash tables is estimated, and each shard of the
+  /// pool only sees what the others had allocated as of their last
+  /// allocation, so the budget isn't exact.
+  #[inline]
+  pub fn set_byte_budget(&self, budget: Option<usize>) {
+    self
+      .byte_budget
+      .store(budget.unwrap_or(usize::MAX), Ordering::Relaxed);
+  }
+
+  /// The maximum number of bytes this pool may allocate, see
+  /// [`Interner::set_byte_budget`]
+  #[inline]
+  pub fn byte_budget(&self) -> Option<usize> {
+    match self.byte_budget.load(Ordering::Relaxed) {
+      usize::MAX => None,
+      budget => Some(budget),
+    }
+  }
+}
+
+/// The error returned when a string can't be interned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InternError {
+  /// A new page of memory couldn't be allocated to store the string, because
+  /// it would exceed the byte budget, or the allocation failed
+  PageAllocation,
+  /// The hash table or [`Sym`][crate::Sym] index couldn't grow to fit the
+  /// string, because it would exceed the byte budget, or the allocation
+  /// failed
+  TableGrowth,
+  /// The string is longer than the longest string the pool can store, which
+  /// is 2^30 - 1 bytes
+  TooLong,
+}
+
+impl Display for InternError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      InternError::PageAllocation => {
+        f.write_str("failed to allocate a new page for the string")
+      },
+      InternError::TableGrowth => {
+        f.write_str("failed to grow the Interner's tables")
+      },
+      InternError::TooLong => f.write_str("the string is too long to intern"),
+    }
+  }
+}
+
+impl ::std::error::Error for InternError {}
+
+impl Drop for Interner {
+  fn drop(&mut self) {
+    // safety: we have exclusive access to the Interner, so there can be no
+    // outstanding readers, and no `PoolStr`s borrowing from the pool.
+    unsafe { self.syms.free() };
+    for shard in &mut self.shards {
+      unsafe { shard.free(&self.storage) };
+    }
+  }
+}
+
+/// A lock-free append-only index of `IBytes`s, indexed by their `Sym` number
+///
+/// The entries are stored in buckets which are never moved or freed (until the
+/// Interner is dropped), bucket `n` having room for `SymIndex::BUCKET_0_LEN <<
+/// n` entries. This means readers never have to wait on the writers.
+///
+/// The writers of every shard share the index, so a writer first reserves a
+/// `Sym` number, then writes its string, and then sets the entry.
+struct SymIndex {
+  /// pointers to the buckets, which are lazily allocated by whichever writer
+  /// needs them first. The entries are null until they're set
+  buckets: [AtomicPtr<AtomicPtr<u8>>; SymIndex::BUCKETS],
+
+  /// the number of `Sym` numbers reserved, some of which may not be set yet
+  len: AtomicU32,
+}
+
+impl SymIndex {
+  /// log2 of the length of the first bucket
+  const BUCKET_0_SHIFT: u32 = 5;
+  /// The length of the first bucket
+  const BUCKET_0_LEN: usize = 1 << Self::BUCKET_0_SHIFT;
+  /// Enough buckets to index any `u32`
+  const BUCKETS: usize = (u32::BITS - Self::BUCKET_0_SHIFT + 1) as usize;
+
+  const fn new() -> Self {
+    SymIndex {
+      buckets: [const { AtomicPtr::new(ptr::null_mut()) }; Self::BUCKETS],
+      len: AtomicU32::new(0),
+    }
+  }
+
+  /// Get the bucket number & the index into that bucket for the given entry
+  #[inline]
+  fn locate(index: usize) -> (usize, usize) {
+    let biased = index + Self::BUCKET_0_LEN;
+    let bucket =
+      (usize::BITS - 1 - biased.leading_zeros() - Self::BUCKET_0_SHIFT)
+        as usize;
+    (bucket, biased - (Self::BUCKET_0_LEN << bucket))
+  }
+
+  /// Locklessly get the `IBytes` with the given `Sym` number, if it's set
+  #[inline]
+  fn get(&self, sym: NonZeroU32) -> Option<IBytes> {
+    if sym.get() > self.len.load(Ordering::Acquire) {
+      return None;
+    }
+    let (bucket, i) = Self::locate(sym.get() as usize - 1);
+    let bucket = self.buckets[bucket].load(Ordering::Acquire);
+    if bucket.is_null() {
+      return None;
+    }
+    // safety: the entry is within the bucket, and buckets live until the
+    // Interner is dropped
+    let entry = unsafe { &*bucket.add(i) }.load(Ordering::Acquire);
+    ptr::NonNull::new(entry).map(IBytes)
+  }
+
+  /// Reserve the next `Sym` number, allocating its bucket if needed
+  #[inline]
+  fn reserve(&self) -> NonZeroU32 {
+    let len = self
+      .len
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |len| {
+        Some(len + 1).filter(|&len| len < STATIC_SYM)
+      })
+      .expect("The Interner has run out of Sym numbers");
+    let (bucket, _) = Self::locate(len as usize);
+    if self.try_alloc_bucket(bucket).is_err() {
+      panic!("failed to allocate the Sym index");
+    }
+    // safety: len + 1 doesn't overflow, so it's never zero
+    unsafe { NonZeroU32::new_unchecked(len + 1) }
+  }
+
+  /// Reserve `Sym` numbers until `last` has been reserved
+  fn reserve_through(&self, last: u32) {
+    while self.len.load(Ordering::Relaxed) < last {
+      self.reserve();
+    }
+  }
+
+  /// Set the entry of a reserved `Sym` number, making it readable
+  ///
+  /// # Safety
+  ///
+  /// - `sym` must have been reserved, and not set yet
+  #[inline]
+  unsafe fn set(&self, sym: NonZeroU32, ibytes: IBytes) {
+    let (bucket, i) = Self::locate(sym.get() as usize - 1);
+    let bucket = self.buckets[bucket].load(Ordering::Acquire);
+    // safety: `reserve` allocated the bucket
+    unsafe { &*bucket.add(i) }.store(ibytes.0.as_ptr(), Ordering::Release);
+  }
+
+  /// Make sure the bucket of the next `Sym` number is allocated, so that
+  /// reserving it doesn't fail
+  ///
+  /// note: the writer of another shard may reserve it first, in which case
+  /// `reserve` may still need to allocate
+  #[inline]
+  fn try_reserve(&self) -> Result<(), ()> {
+    let (bucket, _) = Self::locate(self.len.load(Ordering::Relaxed) as usize);
+    self.try_alloc_bucket(bucket)
+  }
+
+  /// Allocate the bucket, unless it already is
+  fn try_alloc_bucket(&self, bucket: usize) -> Result<(), ()> {
+    if !self.buckets[bucket].load(Ordering::Acquire).is_null() {
+      return Ok(());
+    }
+    let layout = Self::bucket_layout(bucket);
+    // note: a zeroed entry is a null pointer, which isn't set yet
+    // safety: buckets are never zero sized
+    let bucket_ptr =
+      unsafe { alloc::alloc_zeroed(layout) } as *mut AtomicPtr<u8>;
+    if bucket_ptr.is_null() {
+      return Err(());
+    }
+    // note: the writers of other shards may race to allocate the same bucket
+    if self.buckets[bucket]
+      .compare_exchange(
+        ptr::null_mut(),
+        bucket_ptr,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+      )
+      .is_err()
+    {
+      // safety: the bucket was just allocated with this layout
+      unsafe { alloc::dealloc(bucket_ptr.cast(), layout) };
+    }
+    Ok(())
+  }
+
+  /// The number of bytes `try_reserve` would allocate
+  #[inline]
+  fn bytes_to_reserve(&self) -> usize {
+    let (bucket, _) = Self::locate(self.len.load(Ordering::Relaxed) as usize);
+    if self.buckets[bucket].load(Ordering::Acquire).is_null() {
+      Self::bucket_layout(bucket).size()
+    } else {
+      0
+    }
+  }
+
+  /// The number of bytes allocated for the buckets
+  #[inline]
+  fn allocated_bytes(&self) -> usize {
+    (0..Self::BUCKETS)
+      .filter(|&bucket| {
+        !self.buckets[bucket].load(Ordering::Acquire).is_null()
+      })
+      .map(|bucket| Self::bucket_layout(bucket).size())
+      .sum()
+  }
+
+  #[inline]
+  fn bucket_layout(bucket: usize) -> Layout {
+    Layout::array::<AtomicPtr<u8>>(Self::BUCKET_0_LEN << bucket).unwrap()
+  }
+
+  /// Free all of the buckets
+  ///
+  /// # Safety
+  ///
+  /// - there must be no outstanding readers, and the index must not be used
+  ///   again
+  unsafe fn free(&mut self) {
+    for (bucket, bucket_ptr) in self.buckets.iter_mut().enumerate() {
+      let bucket_ptr = bucket_ptr.get_mut();
+      if !bucket_ptr.is_null() {
+        // safety: the bucket was allocated with this layout
+        unsafe {
+          alloc::dealloc(bucket_ptr.cast(), Self::bucket_layout(bucket))
+        };
+      }
+    }
   }
 }
 
@@ -385,50 +1486,122 @@ struct Page {
   // held.
   // TODO store this pointer in the memory to avoid the extra layer of
   // indirection
-  next_page: MaybeUninit<OnceCell<&'static Page>>,
+  next_page: MaybeUninit<OnceCell<PagePtr>>,
   // A page of memory containing the bytes of our interned data. The size of
   // the page is dynamic and determined by the len of the slice.
-  mem: UnsafeCell<&'static mut [u8]>,
+  //
+  // note: no reference to the whole page is ever created, since the strings
+  // already interned borrow from it while new records are written. Records
+  // are written through this pointer, and every `IBytes` is derived from it.
+  mem: ptr::NonNull<[u8]>,
+  // safety: `used` may *only* be read or written to while `write_lock` is
+  // held. The number of bytes used, set when the next page is created. The
+  // last page's usage is `last_memory_index` instead.
+  used: Cell<usize>,
+}
+
+/// A pointer to a leaked [`Page`], which is freed when the Interner is dropped
+///
+/// note: the pointer from the `Box` is kept, rather than a `&'static Page`,
+/// since a pointer derived from a shared reference isn't allowed to free it
+#[derive(Clone, Copy)]
+struct PagePtr(ptr::NonNull<Page>);
+
+impl PagePtr {
+  /// Create a new page using the given memory
+  #[inline]
+  fn new(mem: ptr::NonNull<[u8]>) -> Self {
+    let page = Box::new(Page {
+      mem,
+      next_page: MaybeUninit::new(OnceCell::new()),
+      used: Cell::new(0),
+    });
+    // safety: the pointer from a box is never null
+    PagePtr(unsafe { ptr::NonNull::new_unchecked(Box::into_raw(page)) })
+  }
+
+  #[inline]
+  fn get(self) -> &'static Page {
+    // safety: pages live until the Interner is dropped
+    unsafe { self.0.as_ref() }
+  }
+}
+
+impl Deref for PagePtr {
+  type Target = Page;
+
+  #[inline]
+  fn deref(&self) -> &Page {
+    self.get()
+  }
 }
 
 impl Page {
   /// A Page has a size some multiple of this value
   const DEFAULT_CAPACITY: usize = 1024;
 
-  /// Create a new page with at least min_capacity bytes available
+  /// round min_capacity up to nearest integer multiple of DEFAULT_CAPACITY.
   #[inline]
-  fn with_min_capacity(min_capacity: usize) -> &'static Self {
-    // round min_capacity up to nearest integer multiple of DEFAULT_CAPACITY.
-    let capacity = ((min_capacity / Self::DEFAULT_CAPACITY)
-      * Self::DEFAULT_CAPACITY)
+  fn capacity_for(min_capacity: usize) -> usize {
+    ((min_capacity / Self::DEFAULT_CAPACITY) * Self::DEFAULT_CAPACITY)
       + (usize::min(1, min_capacity % Self::DEFAULT_CAPACITY)
-        * Self::DEFAULT_CAPACITY);
+        * Self::DEFAULT_CAPACITY)
+  }
 
-    let mem = vec![0; capacity];
-    let mem = Box::leak(mem.into_boxed_slice());
+  /// Try to create a new page with `capacity` bytes available
+  #[inline]
+  fn try_with_capacity(capacity: usize, storage: &Storage) -> Option<PagePtr> {
+    let mem = storage.try_alloc(capacity)?;
+    Some(PagePtr::new(mem))
+  }
 
-    Box::leak(Box::new(Page {
-      mem: UnsafeCell::new(mem),
-      next_page: MaybeUninit::new(OnceCell::new()),
-    }))
+  /// The number of bytes in this page
+  #[inline]
+  fn capacity(&self) -> usize {
+    self.mem.len()
   }
 
-  /// Panics if the `next_page` field is already occupied
+  /// A pointer to the byte at `index`, which may be used to read the rest of
+  /// the page
+  ///
+  /// # Safety
+  ///
+  /// - `index` must be within the page
+  #[inline]
+  unsafe fn ptr(&self, index: usize) -> ptr::NonNull<u8> {
+    debug_assert!(index < self.capacity());
+    unsafe { self.mem.cast::<u8>().add(index) }
+  }
+
+  /// The `len` bytes starting at `index`
+  ///
+  /// # Safety
+  ///
+  /// - the bytes must be within the page, and must not be written to for as
+  ///   long as the slice lives
+  #[cfg(feature = "mmap")]
+  #[inline]
+  unsafe fn bytes(&self, index: usize, len: usize) -> &'static [u8] {
+    debug_assert!(index + len <= self.capacity());
+    unsafe { ::core::slice::from_raw_parts(self.ptr(index).as_ptr(), len) }
+  }
+
+  /// Copy `bytes` into the page, starting at `index`
   ///
   /// # Safety
   ///
   /// - must only be called while holding the `write_lock`
+  /// - the bytes must be within the page, and there must be no references to
+  ///   them
   #[inline]
-  unsafe fn extend_with_new_page(&self, min_capacity: usize) {
-    // safety: `next_page` will be initialised if the write_lock is held
-    let next_page = unsafe { self.next_page.assume_init_ref() };
-    if next_page.get().is_some() {
-      panic!("The next_page already exists");
-    }
-    #[allow(clippy::needless_borrow)] // this lint is wrong here??
-    let len = unsafe { &*self.mem.get() }.len();
-    // next page should be double the size of the current page (at least)
-    let min_capacity = usize::max(len * 2, min_capacity);
-    let _ = next_page.set(Page::with_min_capacity(min_capacity));
+  unsafe fn write(&self, index: usize, bytes: &[u8]) {
+    debug_assert!(index + bytes.len() <= self.capacity());
+    unsafe {
+      ptr::copy_nonoverlapping(
+        bytes.as_ptr(),
+        self.mem.cast::<u8>().as_ptr().add(index),
+        bytes.len(),
+      )
+    }
   }
 }
@@ -1,18 +1,34 @@
 use {
+  crate::{
+    interner::{LEN_FLAGS, LEN_HAS_NUL},
+    InternError, Sym,
+  },
   ::core::{
     borrow::Borrow,
+    cmp::Ordering,
     convert::AsRef,
     ffi::CStr,
     fmt::{self, Debug, Display},
     hash::Hash,
+    num::NonZeroU32,
     ops::Deref,
+    ptr::NonNull,
+    slice, str,
   },
   ::std::ffi::CString,
 };
 
 /// An Interned string
-#[derive(Eq, Copy, Clone, PartialOrd, Ord)]
-pub struct IStr(pub(super) &'static str);
+///
+/// An `IStr` is a single thin pointer to the string data in the pool; the
+/// length, [`Sym`] number & wyhash of the string are stored in a header just
+/// before it.
+#[derive(Eq, Copy, Clone)]
+pub struct IStr(pub(super) NonNull<u8>);
+
+// safety: an `IStr` is an immutable reference to `'static` data
+unsafe impl Send for IStr {}
+unsafe impl Sync for IStr {}
 
 /// Create a collection of all the currently interned strings
 ///
@@ -27,7 +43,7 @@ pub fn collect_interned_strings<B>() -> B
 where
   B: ::core::iter::FromIterator<IStr>,
 {
-  crate::interner::THE_INTERNER.collect_interned_strings()
+  crate::interner::THE_INTERNER.collect_istrs()
 }
 
 // # constructors
@@ -36,12 +52,100 @@ macro_rules! intern_doc {() => {
 r"Intern a new string, or return the extant [`IStr`] if one exists
 
 This operation may be slow, depending on whether the string has been previously
-interned."
+interned.
+
+Panics if the memory needed to store a new string can't be allocated, or the
+string is too long to store, see [`try_intern`][crate::try_intern]."
 };}
 #[doc = intern_doc!()]
 #[inline]
 pub fn intern(s: &str) -> IStr {
-  crate::interner::THE_INTERNER.intern(s)
+  #[cfg(feature = "thread-cache")]
+  if let Some(istr) = crate::thread_cache::get(s) {
+    return istr;
+  }
+  let istr = crate::interner::THE_INTERNER.intern_istr(s);
+  #[cfg(feature = "thread-cache")]
+  crate::thread_cache::insert(istr);
+  istr
+}
+
+/// Intern a new string, or return the extant [`IStr`] if one exists
+///
+/// Unlike [`intern`], this returns an error instead of panicking if the memory
+/// needed to store a new string can't be allocated, or would exceed the byte
+/// budget set with [`set_byte_budget`], or if the string is longer than 2^30 -
+/// 1 bytes.
+///
+/// ```rust
+/// # use sinter::{try_intern, IStr};
+/// let istr: IStr = try_intern("fallible").unwrap();
+/// ```
+#[inline]
+pub fn try_intern(s: &str) -> Result<IStr, InternError> {
+  #[cfg(feature = "thread-cache")]
+  if let Some(istr) = crate::thread_cache::get(s) {
+    return Ok(istr);
+  }
+  let istr = crate::interner::THE_INTERNER.try_intern_istr(s)?;
+  #[cfg(feature = "thread-cache")]
+  crate::thread_cache::insert(istr);
+  Ok(istr)
+}
+
+/// Intern a batch of strings, returning the [`IStr`]s in the same order
+///
+/// This is much faster than calling [`intern`] for each string when many of
+/// them are new, such as when loading a large symbol file: every string is
+/// hashed & looked up up front, and the missing ones are all added while
+/// taking the lock of each shard of the pool they belong to once.
+///
+/// Panics if the memory needed to store the new strings can't be allocated.
+///
+/// ```rust
+/// # use sinter::{intern, intern_many, IStr};
+/// let istrs: Vec<IStr> = intern_many(["fn", "let", "fn"]);
+/// assert!(istrs[0] == intern("fn"));
+/// assert!(istrs[0] == istrs[2]);
+/// ```
+#[inline]
+pub fn intern_many<I>(strs: I) -> Vec<IStr>
+where
+  I: IntoIterator,
+  I::Item: AsRef<str>,
+{
+  crate::interner::THE_INTERNER.intern_many_istrs(strs)
+}
+
+/// Intern a batch of strings, appending the [`IStr`]s to `out` in the same
+/// order
+///
+/// See [`intern_many`].
+///
+/// ```rust
+/// # use sinter::{intern_many_into, IStr};
+/// let mut istrs: Vec<IStr> = Vec::new();
+/// intern_many_into(["a", "b"], &mut istrs);
+/// intern_many_into(vec![String::from("c")], &mut istrs);
+/// assert_eq!(istrs, ["a", "b", "c"]);
+/// ```
+#[inline]
+pub fn intern_many_into<I, E>(strs: I, out: &mut E)
+where
+  I: IntoIterator,
+  I::Item: AsRef<str>,
+  E: Extend<IStr>,
+{
+  out.extend(intern_many(strs));
+}
+
+/// Set the maximum number of bytes the global pool may allocate, or `None` for
+/// no limit
+///
+/// See [`Interner::set_byte_budget`][crate::Interner::set_byte_budget].
+#[inline]
+pub fn set_byte_budget(budget: Option<usize>) {
+  crate::interner::THE_INTERNER.set_byte_budget(budget)
 }
 
 /// Locklessly find an extant [`IStr`] corresponding to the string given, if
@@ -51,7 +155,14 @@ pub fn intern(s: &str) -> IStr {
 /// interning it if not.
 #[inline]
 pub fn get_interned(s: &str) -> Option<IStr> {
-  crate::interner::THE_INTERNER.get_interned(s)
+  #[cfg(feature = "thread-cache")]
+  if let Some(istr) = crate::thread_cache::get(s) {
+    return Some(istr);
+  }
+  let istr = crate::interner::THE_INTERNER.get_interned_istr(s)?;
+  #[cfg(feature = "thread-cache")]
+  crate::thread_cache::insert(istr);
+  Some(istr)
 }
 
 impl IStr {
@@ -126,18 +237,22 @@ impl Deref for IStr {
 
   #[inline]
   fn deref(&self) -> &str {
-    self.0
+    self.as_str()
   }
 }
 
 impl AsRef<str> for IStr {
   #[inline]
   fn as_ref(&self) -> &str {
-    self.0
+    self.as_str()
   }
 }
 
 impl AsRef<CStr> for IStr {
+  /// # Panics
+  ///
+  /// Panics if the string contains an interior null byte, see
+  /// [`IStr::try_as_c_str`]
   #[inline]
   fn as_ref(&self) -> &CStr {
     self.as_c_str()
@@ -147,7 +262,7 @@ impl AsRef<CStr> for IStr {
 impl Borrow<str> for IStr {
   #[inline]
   fn borrow(&self) -> &'static str {
-    self.0
+    self.as_str()
   }
 }
 
@@ -155,18 +270,106 @@ impl IStr {
   /// get the underlying `&str`
   #[inline]
   pub fn as_str(&self) -> &'static str {
-    self.0
+    // safety: the Interner stores the length of the string in the header, and
+    // only ever stores valid utf-8
+    unsafe {
+      let bytes = slice::from_raw_parts(self.0.as_ptr(), self.header_len());
+      str::from_utf8_unchecked(bytes)
+    }
+  }
+
+  /// The length of the string, as stored in the header
+  #[inline]
+  fn header_len(&self) -> usize {
+    (self.header_len_word() & !LEN_FLAGS) as usize
+  }
+
+  /// The length of the string, combined with the flags, as stored in the
+  /// header
+  #[inline]
+  fn header_len_word(&self) -> u32 {
+    use crate::interner::{SIZE_OF_LEN, SIZE_OF_WYHASH};
+    // safety: the Interner stores the u32 length of the string in the header
+    // preceding the wyhash
+    let len_array: &[u8; SIZE_OF_LEN] = unsafe {
+      let len_ptr = self.0.as_ptr().sub(SIZE_OF_WYHASH + SIZE_OF_LEN);
+      &*(len_ptr as *const [u8; SIZE_OF_LEN])
+    };
+    u32::from_ne_bytes(*len_array)
   }
 
   /// zero-cost conversion to a null terminated [`CStr`]
+  ///
+  /// # Panics
+  ///
+  /// Panics if the string contains an interior null byte, since the `CStr`
+  /// would silently end early; see [`IStr::try_as_c_str`].
   #[inline]
+  #[track_caller]
   pub fn as_c_str(&self) -> &'static CStr {
-    let ptr = self.0.as_ptr();
+    match self.try_as_c_str() {
+      Ok(c_str) => c_str,
+      Err(e) => panic!("{e}"),
+    }
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`], or an error if the
+  /// string contains an interior null byte
+  ///
+  /// The Interner checks the string for null bytes once when it's interned,
+  /// and stores the result next to it, so this is free.
+  ///
+  /// ```rust
+  /// # use sinter::intern;
+  /// assert_eq!(intern("a").try_as_c_str(), Ok(c"a"));
+  /// assert_eq!(intern("a\0b").try_as_c_str().unwrap_err().nul_position(), 1);
+  /// ```
+  #[inline]
+  pub fn try_as_c_str(&self) -> Result<&'static CStr, NulError> {
+    let bytes = self.as_bytes_with_nul();
+    if self.header_len_word() & LEN_HAS_NUL != 0 {
+      // note: this is only slow when there is an interior null byte
+      let position = bytes.iter().position(|&b| b == 0).unwrap();
+      return Err(NulError(position));
+    }
+    // safety: The Interner always leaves a trailing null byte, and there are
+    // no others
+    Ok(unsafe { CStr::from_bytes_with_nul_unchecked(bytes) })
+  }
+
+  /// get the underlying bytes, including the trailing null byte
+  #[inline]
+  fn as_bytes_with_nul(&self) -> &'static [u8] {
     // safety: The Interner always leaves a trailing null byte
-    unsafe { CStr::from_ptr(ptr as _) }
+    unsafe { slice::from_raw_parts(self.0.as_ptr(), self.header_len() + 1) }
+  }
+}
+
+/// An error indicating that an [`IStr`] can't be converted to a [`CStr`],
+/// because it contains an interior null byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NulError(usize);
+
+impl NulError {
+  /// The position of the first null byte in the string
+  #[inline]
+  pub fn nul_position(&self) -> usize {
+    self.0
+  }
+}
+
+impl Display for NulError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "the string contains an interior null byte at position {}",
+      self.0
+    )
   }
 }
 
+impl ::std::error::Error for NulError {}
+
 impl From<IStr> for &'static str {
   #[inline]
   fn from(i: IStr) -> &'static str {
@@ -175,6 +378,10 @@ impl From<IStr> for &'static str {
 }
 
 impl From<IStr> for &'static CStr {
+  /// # Panics
+  ///
+  /// Panics if the string contains an interior null byte, see
+  /// [`IStr::try_as_c_str`]
   #[inline]
   fn from(i: IStr) -> &'static CStr {
     i.as_c_str()
@@ -184,11 +391,15 @@ impl From<IStr> for &'static CStr {
 impl From<IStr> for String {
   #[inline]
   fn from(s: IStr) -> String {
-    s.0.to_owned()
+    s.as_str().to_owned()
   }
 }
 
 impl From<IStr> for CString {
+  /// # Panics
+  ///
+  /// Panics if the string contains an interior null byte, see
+  /// [`IStr::try_as_c_str`]
   #[inline]
   fn from(s: IStr) -> CString {
     s.as_c_str().to_owned()
@@ -197,6 +408,11 @@ impl From<IStr> for CString {
 
 impl IStr {
   /// Create a new [`CString`] from the value
+  ///
+  /// # Panics
+  ///
+  /// Panics if the string contains an interior null byte, see
+  /// [`IStr::try_as_c_str`]
   #[inline]
   pub fn to_c_string(&self) -> CString {
     self.as_c_str().to_owned()
@@ -207,14 +423,14 @@ impl IStr {
 impl Display for IStr {
   #[inline]
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.write_str(self.0)
+    f.write_str(self.as_str())
   }
 }
 
 impl Debug for IStr {
   #[inline]
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    f.write_fmt(format_args!("IStr(\"{}\")", self.0))
+    f.write_fmt(format_args!("IStr(\"{}\")", self.as_str()))
   }
 }
 
@@ -228,7 +444,22 @@ impl PartialEq for IStr {
     // it is sufficient to compare the pointers, because the Interner never
     // produces two distinct [`IStr`]s with the same data, and does not alias
     // strings in the pool.
-    ::core::ptr::eq(self.0.as_ptr(), rhs.0.as_ptr())
+    self.0 == rhs.0
+  }
+}
+
+impl PartialOrd for IStr {
+  #[inline]
+  fn partial_cmp(&self, rhs: &IStr) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl Ord for IStr {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn cmp(&self, rhs: &IStr) -> Ordering {
+    self.as_str().cmp(rhs.as_str())
   }
 }
 
@@ -237,14 +468,14 @@ impl PartialEq<&str> for IStr {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &&str) -> bool {
-    self.0 == *other
+    self.as_str() == *other
   }
 }
 impl PartialEq<IStr> for &str {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &IStr) -> bool {
-    *self == other.0
+    *self == other.as_str()
   }
 }
 
@@ -253,14 +484,14 @@ impl PartialEq<&CStr> for IStr {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &&CStr) -> bool {
-    self.as_c_str() == *other
+    self.as_bytes() == other.to_bytes()
   }
 }
 impl PartialEq<IStr> for &CStr {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &IStr) -> bool {
-    *self == other.as_c_str()
+    self.to_bytes() == other.as_bytes()
   }
 }
 
@@ -269,28 +500,28 @@ impl PartialEq<String> for IStr {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &String) -> bool {
-    self.0 == other
+    self.as_str() == other
   }
 }
 impl PartialEq<IStr> for String {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &IStr) -> bool {
-    self == other.0
+    self == other.as_str()
   }
 }
 impl PartialEq<&String> for IStr {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &&String) -> bool {
-    self.0 == *other
+    self.as_str() == *other
   }
 }
 impl PartialEq<IStr> for &String {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &IStr) -> bool {
-    *self == other.0
+    *self == other.as_str()
   }
 }
 
@@ -299,32 +530,28 @@ impl PartialEq<CString> for IStr {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &CString) -> bool {
-    let o: &CStr = other;
-    self.as_c_str() == o
+    self.as_bytes() == other.as_bytes()
   }
 }
 impl PartialEq<IStr> for CString {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &IStr) -> bool {
-    let s: &CStr = self;
-    s == other.as_c_str()
+    self.as_bytes() == other.as_bytes()
   }
 }
 impl PartialEq<&CString> for IStr {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &&CString) -> bool {
-    let o: &CStr = other;
-    self.as_c_str() == o
+    self.as_bytes() == other.as_bytes()
   }
 }
 impl PartialEq<IStr> for &CString {
   /// full (potentially slow) string comparison
   #[inline]
   fn eq(&self, other: &IStr) -> bool {
-    let s: &CStr = self;
-    s == other.as_c_str()
+    self.as_bytes() == other.as_bytes()
   }
 }
 
@@ -357,4 +584,27 @@ impl IStr {
     };
     u64::from_ne_bytes(*hash_array)
   }
+
+  /// The compact [`Sym`] handle for this string
+  ///
+  /// The Interner stores the [`Sym`] number next to the string so this method
+  /// call is free.
+  ///
+  /// Panics if this is a string of a [`StaticTable`][crate::StaticTable] that
+  /// hasn't been registered.
+  #[inline]
+  pub fn sym(&self) -> Sym {
+    use crate::interner::{SIZE_OF_HEADER, SIZE_OF_SYM, STATIC_SYM};
+    // safety: the Interner stores the u32 sym number at the start of the
+    // header preceding the string data
+    let sym_ptr = unsafe { self.0.as_ptr().sub(SIZE_OF_HEADER) };
+    let sym_array = unsafe { &*(sym_ptr as *const [u8; SIZE_OF_SYM]) };
+    let sym = u32::from_ne_bytes(*sym_array);
+    if sym & STATIC_SYM != 0 {
+      // safety: only static tables set the flag
+      return unsafe { crate::static_table::static_sym(sym_ptr, sym) };
+    }
+    // safety: the Interner never gives out a sym number of zero
+    Sym(unsafe { NonZeroU32::new_unchecked(sym) })
+  }
 }
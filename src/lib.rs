@@ -1,9 +1,64 @@
 #![doc = include_str!("../README.md")]
 
+mod const_wyhash;
+mod double_table;
 mod ext;
+mod generation;
+mod ibytes;
+mod ilist;
+mod interned;
 mod interner;
 mod istr;
-#[cfg(any(test, doctest))]
+#[cfg(all(test, loom))]
+mod loom_tests;
+mod macros;
+mod os_str;
+mod pool_str;
+mod rc_istr;
+#[cfg(feature = "serde")]
+mod serde;
+pub mod snapshot;
+mod static_table;
+mod stats;
+mod storage;
+mod sym;
+mod sync;
+pub mod table;
+#[cfg(all(any(test, doctest), not(loom)))]
 mod tests;
+#[cfg(feature = "thread-cache")]
+mod thread_cache;
 
-pub use istr::{collect_interned_strings, get_interned, intern, IStr};
+pub use {
+  generation::{GenStr, Generation},
+  ibytes::{get_interned_bytes, intern_bytes, try_intern_bytes, IBytes},
+  ilist::{IList, IListPool},
+  interned::{InternPool, Interned},
+  interner::{InternError, Interner},
+  istr::{
+    collect_interned_strings, get_interned, intern, intern_many,
+    intern_many_into, set_byte_budget, try_intern, IStr, NulError,
+  },
+  os_str::{IOsStr, IPath},
+  pool_str::PoolStr,
+  rc_istr::RcIStr,
+  static_table::{RegisterError, StaticTable},
+  stats::{stats, PageStats, Stats},
+  sym::Sym,
+};
+
+#[cfg(feature = "counters")]
+pub use stats::{counters, Counters};
+
+#[doc(hidden)]
+pub mod __private {
+  pub use crate::{
+    const_wyhash::wyhash as const_wyhash,
+    interner::WYHASH_SEED,
+    macros::IStrCache,
+    static_table::{
+      static_table_bytes, static_table_offsets, static_table_size,
+      StaticTableData,
+    },
+  };
+}
@@ -0,0 +1,187 @@
+//! Model tests of the lock-free tables, run with:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --release --lib loom
+//! ```
+//!
+//! loom checks that the writer never touches a table while a reader could
+//! still be using it, since the tables are kept in loom's `UnsafeCell`s.
+
+use {
+  super::*,
+  ::loom::{model::Builder, sync::Arc, thread},
+};
+
+/// Run `f` under loom, with a bound on the preemptions explored so that the
+/// models finish in a reasonable time
+///
+/// note: the models only read from spawned threads, since loom drops the
+/// main thread's epoch after the model has finished
+fn model(f: impl Fn() + Sync + Send + 'static) {
+  model_with(3, f);
+}
+
+/// Run `f` under loom, exploring up to `preemptions` preemptions
+fn model_with(preemptions: usize, f: impl Fn() + Sync + Send + 'static) {
+  let mut builder = Builder::new();
+  builder.preemption_bound.get_or_insert(preemptions);
+  // note: the spin locks of contended writers take a lot of branches
+  builder.max_branches = 10_000;
+  builder.check(f);
+}
+
+#[test]
+fn loom_intern() {
+  model(|| {
+    let interner = Arc::new(Interner::new());
+    let handles: Vec<_> = (0..2)
+      .map(|_| {
+        let interner = interner.clone();
+        thread::spawn(move || interner.intern("a").id())
+      })
+      .collect();
+    let ids: Vec<u32> =
+      handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(ids, [1, 1]);
+    assert_eq!(interner.stats().strings, 1);
+  });
+}
+
+#[test]
+fn loom_get_interned() {
+  model(|| {
+    let interner = Arc::new(Interner::new());
+    let reader = {
+      let interner = interner.clone();
+      thread::spawn(move || {
+        for _ in 0..2 {
+          if let Some(a) = interner.get_interned("a") {
+            assert_eq!(a, "a");
+            assert_eq!(a.id(), 1);
+          }
+        }
+      })
+    };
+    // note: every new string swaps the tables, so the second & third write to
+    // the table the reader may have been using
+    let writer = {
+      let interner = interner.clone();
+      thread::spawn(move || {
+        for s in ["a", "b", "c"] {
+          interner.intern(s);
+        }
+        assert_eq!(interner.get_interned("c").unwrap().id(), 3);
+      })
+    };
+    reader.join().unwrap();
+    writer.join().unwrap();
+  });
+}
+
+#[test]
+fn loom_collect_interned_strings() {
+  model(|| {
+    let interner = Arc::new(Interner::new());
+    let reader = {
+      let interner = interner.clone();
+      thread::spawn(move || {
+        let mut strings: Vec<String> = interner
+          .collect_interned_strings::<Vec<_>>()
+          .into_iter()
+          .map(String::from)
+          .collect();
+        strings.sort();
+        assert!(
+          strings.is_empty() || strings == ["a"] || strings == ["a", "b"],
+          "{strings:?}"
+        );
+      })
+    };
+    let writer = {
+      let interner = interner.clone();
+      thread::spawn(move || {
+        interner.intern("a");
+        interner.intern("b");
+        assert_eq!(interner.collect_interned_strings::<Vec<_>>().len(), 2);
+      })
+    };
+    reader.join().unwrap();
+    writer.join().unwrap();
+  });
+}
+
+#[test]
+fn loom_nested_read() {
+  use crate::double_table::DoubleTable;
+
+  model(|| {
+    let tables =
+      Arc::new((DoubleTable::<u64>::new(), DoubleTable::<u64>::new()));
+    let reader = {
+      let tables = tables.clone();
+      thread::spawn(move || {
+        tables.0.read(|outer| {
+          tables.1.read(|_| {});
+          // note: the writer mustn't touch the outer table until this is done
+          if let Some(outer) = outer {
+            assert!(outer.find(1, |&v| v == 1).is_some());
+          }
+        });
+      })
+    };
+    // note: the second & third writes are to the table the reader may be
+    // using
+    let writer = thread::spawn(move || {
+      for i in 1..=3 {
+        // safety: this is the only writer
+        unsafe {
+          tables.0.writer_table();
+          tables.0.publish(i, i, |&v| v);
+        }
+      }
+    });
+    reader.join().unwrap();
+    writer.join().unwrap();
+  });
+}
+
+#[test]
+fn loom_two_writers() {
+  use crate::double_table::DoubleTable;
+
+  // note: with three threads, more preemptions take too long to explore
+  model_with(1, || {
+    let tables =
+      Arc::new((DoubleTable::<u64>::new(), DoubleTable::<u64>::new()));
+    let reader = {
+      let tables = tables.clone();
+      thread::spawn(move || {
+        tables.0.read(|table| {
+          if let Some(table) = table {
+            assert!(table.find(1, |&v| v == 1).is_some());
+          }
+        });
+      })
+    };
+    // note: the writers of the two tables wait for the reader at the same
+    // time
+    let writers: Vec<_> = [0, 1]
+      .into_iter()
+      .map(|t| {
+        let tables = tables.clone();
+        thread::spawn(move || {
+          let table = if t == 0 { &tables.0 } else { &tables.1 };
+          // safety: this is the only writer of the table
+          unsafe {
+            table.writer_table();
+            table.publish(1, 1, |&v| v);
+          }
+        })
+      })
+      .collect();
+    reader.join().unwrap();
+    for writer in writers {
+      writer.join().unwrap();
+    }
+  });
+}
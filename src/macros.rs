@@ -0,0 +1,66 @@
+use {
+  crate::IStr,
+  ::core::{
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, Ordering},
+  },
+};
+
+/// Intern a string literal, caching the [`IStr`] at the call site
+///
+/// The wyhash of the literal is computed at compile time, and the [`IStr`] is
+/// stored in a `static` unique to each call site after the first call. So
+/// subsequent calls are just a single atomic load, making this much cheaper
+/// than calling [`intern`][crate::intern] in a hot loop.
+///
+/// The [`IStr`] returned is the same one [`intern`][crate::intern] would
+/// return.
+///
+/// ```rust
+/// # use sinter::{intern, istr};
+/// for _ in 0..3 {
+///   assert!(istr!("identifier") == intern("identifier"));
+/// }
+/// ```
+#[macro_export]
+macro_rules! istr {
+  ($s:literal) => {{
+    const S: &str = $s;
+    const WYHASH: u64 = $crate::__private::const_wyhash(
+      S.as_bytes(),
+      $crate::__private::WYHASH_SEED,
+    );
+    static CACHE: $crate::__private::IStrCache =
+      $crate::__private::IStrCache::new();
+    CACHE.get_or_intern(S, WYHASH)
+  }};
+}
+
+/// A lazily initialised [`IStr`], used by the [`istr!`] macro
+#[doc(hidden)]
+pub struct IStrCache(AtomicPtr<u8>);
+
+impl IStrCache {
+  #[inline]
+  pub const fn new() -> Self {
+    IStrCache(AtomicPtr::new(ptr::null_mut()))
+  }
+
+  /// note: `s_wyhash` must be the wyhash of `s`
+  #[inline]
+  pub fn get_or_intern(&self, s: &str, s_wyhash: u64) -> IStr {
+    match NonNull::new(self.0.load(Ordering::Acquire)) {
+      Some(ptr) => IStr(ptr),
+      None => self.intern(s, s_wyhash),
+    }
+  }
+
+  #[cold]
+  fn intern(&self, s: &str, s_wyhash: u64) -> IStr {
+    let istr =
+      crate::interner::THE_INTERNER.intern_istr_with_wyhash(s, s_wyhash);
+    // note: racing threads will all store the same `IStr`
+    self.0.store(istr.0.as_ptr(), Ordering::Release);
+    istr
+  }
+}
@@ -0,0 +1,432 @@
+use {
+  crate::{intern_bytes, IBytes, IStr, NulError},
+  ::core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    convert::AsRef,
+    ffi::CStr,
+    fmt::{self, Debug},
+    hash::Hash,
+    ops::Deref,
+  },
+  ::std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+  },
+};
+
+/// An Interned [`OsStr`]
+///
+/// An `IOsStr` is stored in the same pool as [`IStr`] & [`IBytes`], as the
+/// platform's encoding of the `OsStr`. When that's valid utf-8 it's the very
+/// same entry as the equivalent [`IStr`].
+///
+/// ```rust
+/// # use sinter::{intern, IOsStr};
+/// # use ::std::ffi::OsStr;
+/// let os_str = IOsStr::new(OsStr::new("os_str"));
+/// assert_eq!(&*os_str, OsStr::new("os_str"));
+/// assert!(os_str.to_istr() == Some(intern("os_str")));
+/// ```
+#[derive(Eq, PartialEq, Copy, Clone)]
+pub struct IOsStr(IBytes);
+
+/// An Interned [`Path`]
+///
+/// An `IPath` is stored in the same pool as [`IStr`] & [`IBytes`], as the
+/// platform's encoding of the `Path`. When that's valid utf-8 it's the very
+/// same entry as the equivalent [`IStr`].
+///
+/// note: two `IPath`s are only equal if their bytes are identical, so unlike
+/// [`Path`], `"a/b"` and `"a//b"` are different `IPath`s.
+///
+/// ```rust
+/// # use sinter::{intern, IPath};
+/// # use ::std::path::Path;
+/// let path = IPath::new("src/lib.rs");
+/// assert_eq!(path.extension().unwrap(), "rs");
+/// assert!(path.to_istr() == Some(intern("src/lib.rs")));
+/// ```
+#[derive(Eq, PartialEq, Copy, Clone)]
+pub struct IPath(IBytes);
+
+impl IOsStr {
+  /// Intern a new [`OsStr`], or return the extant [`IOsStr`] if one exists
+  #[inline]
+  pub fn new<S: AsRef<OsStr> + ?Sized>(s: &S) -> Self {
+    IOsStr(intern_bytes(s.as_ref().as_encoded_bytes()))
+  }
+
+  /// get the underlying `&OsStr`
+  #[inline]
+  pub fn as_os_str(&self) -> &'static OsStr {
+    // safety: the bytes were interned from the encoded bytes of an `OsStr`
+    // (or a `str`, which is always a valid `OsStr`), and the Interner stores
+    // the exact same bytes
+    unsafe { OsStr::from_encoded_bytes_unchecked(self.0.as_bytes()) }
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`], using the platform's
+  /// encoding of the `OsStr`
+  ///
+  /// # Panics
+  ///
+  /// Panics if the `OsStr` contains an interior null byte, see
+  /// [`IOsStr::try_as_c_str`].
+  #[inline]
+  #[track_caller]
+  pub fn as_c_str(&self) -> &'static CStr {
+    // note: an `IStr` has the same representation, and its `CStr` methods
+    // don't depend on the bytes being utf-8
+    IStr(self.0 .0).as_c_str()
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`], using the platform's
+  /// encoding of the `OsStr`, or an error if it contains an interior null byte
+  #[inline]
+  pub fn try_as_c_str(&self) -> Result<&'static CStr, NulError> {
+    IStr(self.0 .0).try_as_c_str()
+  }
+
+  /// Cheaply convert to the equivalent [`IStr`], if the `OsStr` is valid
+  /// utf-8
+  #[inline]
+  pub fn to_istr(self) -> Option<IStr> {
+    self.0.to_istr()
+  }
+
+  /// The interned bytes of the platform's encoding of the `OsStr`
+  #[inline]
+  pub fn as_ibytes(self) -> IBytes {
+    self.0
+  }
+
+  /// The [wyhash](https://crates.io/crates/wyhash) value of the encoded bytes
+  ///
+  /// This value is cached next to the bytes by the interner so this method
+  /// call is free.
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    self.0.wyhash()
+  }
+}
+
+impl IPath {
+  /// Intern a new [`Path`], or return the extant [`IPath`] if one exists
+  #[inline]
+  pub fn new<P: AsRef<Path> + ?Sized>(p: &P) -> Self {
+    IPath(IOsStr::new(p.as_ref()).0)
+  }
+
+  /// get the underlying `&Path`
+  #[inline]
+  pub fn as_path(&self) -> &'static Path {
+    Path::new(self.as_os_str())
+  }
+
+  /// get the underlying `&OsStr`
+  #[inline]
+  pub fn as_os_str(&self) -> &'static OsStr {
+    IOsStr(self.0).as_os_str()
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`], using the platform's
+  /// encoding of the `Path`
+  ///
+  /// # Panics
+  ///
+  /// Panics if the `Path` contains an interior null byte, see
+  /// [`IPath::try_as_c_str`].
+  #[inline]
+  #[track_caller]
+  pub fn as_c_str(&self) -> &'static CStr {
+    IOsStr(self.0).as_c_str()
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`], using the platform's
+  /// encoding of the `Path`, or an error if it contains an interior null byte
+  #[inline]
+  pub fn try_as_c_str(&self) -> Result<&'static CStr, NulError> {
+    IOsStr(self.0).try_as_c_str()
+  }
+
+  /// Cheaply convert to the equivalent [`IStr`], if the `Path` is valid
+  /// utf-8
+  #[inline]
+  pub fn to_istr(self) -> Option<IStr> {
+    self.0.to_istr()
+  }
+
+  /// The interned bytes of the platform's encoding of the `Path`
+  #[inline]
+  pub fn as_ibytes(self) -> IBytes {
+    self.0
+  }
+
+  /// The [wyhash](https://crates.io/crates/wyhash) value of the encoded bytes
+  ///
+  /// This value is cached next to the bytes by the interner so this method
+  /// call is free.
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    self.0.wyhash()
+  }
+}
+
+// # constructors
+
+impl From<&OsStr> for IOsStr {
+  /// Intern a new [`OsStr`], or return the extant [`IOsStr`] if one exists
+  #[inline]
+  fn from(s: &OsStr) -> Self {
+    IOsStr::new(s)
+  }
+}
+
+impl From<OsString> for IOsStr {
+  /// Intern a new [`OsStr`], or return the extant [`IOsStr`] if one exists
+  #[inline]
+  fn from(s: OsString) -> Self {
+    IOsStr::new(&s)
+  }
+}
+
+impl From<&Path> for IPath {
+  /// Intern a new [`Path`], or return the extant [`IPath`] if one exists
+  #[inline]
+  fn from(p: &Path) -> Self {
+    IPath::new(p)
+  }
+}
+
+impl From<PathBuf> for IPath {
+  /// Intern a new [`Path`], or return the extant [`IPath`] if one exists
+  #[inline]
+  fn from(p: PathBuf) -> Self {
+    IPath::new(&p)
+  }
+}
+
+impl From<IStr> for IOsStr {
+  /// Free conversion, a `str` is always a valid `OsStr`
+  #[inline]
+  fn from(istr: IStr) -> Self {
+    IOsStr(istr.into())
+  }
+}
+
+impl From<IStr> for IPath {
+  /// Free conversion, a `str` is always a valid `Path`
+  #[inline]
+  fn from(istr: IStr) -> Self {
+    IPath(istr.into())
+  }
+}
+
+impl From<IOsStr> for IPath {
+  #[inline]
+  fn from(s: IOsStr) -> Self {
+    IPath(s.0)
+  }
+}
+
+impl From<IPath> for IOsStr {
+  #[inline]
+  fn from(p: IPath) -> Self {
+    IOsStr(p.0)
+  }
+}
+
+impl From<IOsStr> for IBytes {
+  #[inline]
+  fn from(s: IOsStr) -> Self {
+    s.0
+  }
+}
+
+impl From<IPath> for IBytes {
+  #[inline]
+  fn from(p: IPath) -> Self {
+    p.0
+  }
+}
+
+// # reference types & conversion
+
+impl Deref for IOsStr {
+  type Target = OsStr;
+
+  #[inline]
+  fn deref(&self) -> &OsStr {
+    self.as_os_str()
+  }
+}
+
+impl AsRef<OsStr> for IOsStr {
+  #[inline]
+  fn as_ref(&self) -> &OsStr {
+    self.as_os_str()
+  }
+}
+
+impl AsRef<Path> for IOsStr {
+  #[inline]
+  fn as_ref(&self) -> &Path {
+    Path::new(self.as_os_str())
+  }
+}
+
+impl Borrow<OsStr> for IOsStr {
+  #[inline]
+  fn borrow(&self) -> &OsStr {
+    self.as_os_str()
+  }
+}
+
+impl Deref for IPath {
+  type Target = Path;
+
+  #[inline]
+  fn deref(&self) -> &Path {
+    self.as_path()
+  }
+}
+
+impl AsRef<Path> for IPath {
+  #[inline]
+  fn as_ref(&self) -> &Path {
+    self.as_path()
+  }
+}
+
+impl AsRef<OsStr> for IPath {
+  #[inline]
+  fn as_ref(&self) -> &OsStr {
+    self.as_os_str()
+  }
+}
+
+impl From<IOsStr> for &'static OsStr {
+  #[inline]
+  fn from(s: IOsStr) -> &'static OsStr {
+    s.as_os_str()
+  }
+}
+
+impl From<IPath> for &'static Path {
+  #[inline]
+  fn from(p: IPath) -> &'static Path {
+    p.as_path()
+  }
+}
+
+impl From<IOsStr> for OsString {
+  #[inline]
+  fn from(s: IOsStr) -> OsString {
+    s.as_os_str().to_owned()
+  }
+}
+
+impl From<IPath> for PathBuf {
+  #[inline]
+  fn from(p: IPath) -> PathBuf {
+    p.as_path().to_owned()
+  }
+}
+
+impl Debug for IOsStr {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "IOsStr({:?})", self.as_os_str())
+  }
+}
+
+impl Debug for IPath {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "IPath({:?})", self.as_path())
+  }
+}
+
+// # equality
+
+impl PartialOrd for IOsStr {
+  #[inline]
+  fn partial_cmp(&self, rhs: &IOsStr) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl Ord for IOsStr {
+  /// full (potentially slow) comparison
+  #[inline]
+  fn cmp(&self, rhs: &IOsStr) -> Ordering {
+    self.as_os_str().cmp(rhs.as_os_str())
+  }
+}
+
+impl PartialOrd for IPath {
+  #[inline]
+  fn partial_cmp(&self, rhs: &IPath) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl Ord for IPath {
+  /// full (potentially slow) comparison of the bytes
+  ///
+  /// note: this is consistent with `Eq`, so unlike [`Path`] it doesn't
+  /// compare component-wise
+  #[inline]
+  fn cmp(&self, rhs: &IPath) -> Ordering {
+    self.as_os_str().cmp(rhs.as_os_str())
+  }
+}
+
+impl PartialEq<&OsStr> for IOsStr {
+  /// full (potentially slow) comparison
+  #[inline]
+  fn eq(&self, other: &&OsStr) -> bool {
+    self.as_os_str() == *other
+  }
+}
+impl PartialEq<IOsStr> for &OsStr {
+  /// full (potentially slow) comparison
+  #[inline]
+  fn eq(&self, other: &IOsStr) -> bool {
+    *self == other.as_os_str()
+  }
+}
+
+impl PartialEq<&Path> for IPath {
+  /// full (potentially slow) component-wise [`Path`] comparison
+  #[inline]
+  fn eq(&self, other: &&Path) -> bool {
+    self.as_path() == *other
+  }
+}
+impl PartialEq<IPath> for &Path {
+  /// full (potentially slow) component-wise [`Path`] comparison
+  #[inline]
+  fn eq(&self, other: &IPath) -> bool {
+    *self == other.as_path()
+  }
+}
+
+// # hashing
+
+impl Hash for IOsStr {
+  /// This feeds the underlying &OsStr into the hasher
+  #[inline]
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.as_os_str().hash(state);
+  }
+}
+
+impl Hash for IPath {
+  /// This feeds the underlying bytes into the hasher
+  #[inline]
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.0.hash(state);
+  }
+}
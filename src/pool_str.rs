@@ -0,0 +1,280 @@
+use {
+  crate::{interner::Interner, IStr, InternError, NulError},
+  ::core::{
+    borrow::Borrow,
+    convert::AsRef,
+    ffi::CStr,
+    fmt::{self, Debug, Display},
+    hash::Hash,
+    marker::PhantomData,
+    ops::Deref,
+  },
+};
+
+/// A string interned in the pool of a particular [`Interner`]
+///
+/// A `PoolStr` borrows from the `Interner` that created it, so it can't
+/// outlive its pool.
+#[derive(Eq, Copy, Clone, PartialOrd, Ord)]
+pub struct PoolStr<'i> {
+  pub(crate) istr: IStr,
+  _interner: PhantomData<&'i Interner>,
+}
+
+impl<'i> PoolStr<'i> {
+  /// note: the caller must ensure the `IStr` lives for at least `'i`
+  #[inline]
+  pub(crate) fn new(istr: IStr) -> Self {
+    PoolStr {
+      istr,
+      _interner: PhantomData,
+    }
+  }
+}
+
+impl Interner {
+  /// Intern a new string, or return the extant [`PoolStr`] if one exists
+  ///
+  /// This operation may be slow, depending on whether the string has been
+  /// previously interned.
+  ///
+  /// Panics if the memory needed to store a new string can't be allocated,
+  /// see [`Interner::try_intern`].
+  #[inline]
+  pub fn intern(&self, s: &str) -> PoolStr<'_> {
+    PoolStr::new(self.intern_istr(s))
+  }
+
+  /// Intern a new string, or return the extant [`PoolStr`] if one exists
+  ///
+  /// Unlike [`Interner::intern`], this returns an error instead of panicking
+  /// if the memory needed to store a new string can't be allocated, or would
+  /// exceed the pool's [byte budget][Interner::set_byte_budget], or if the
+  /// string is longer than 2^30 - 1 bytes.
+  #[inline]
+  pub fn try_intern(&self, s: &str) -> Result<PoolStr<'_>, InternError> {
+    self.try_intern_istr(s).map(PoolStr::new)
+  }
+
+  /// Intern a batch of strings, returning the [`PoolStr`]s in the same order
+  ///
+  /// The missing strings are all added while taking each shard's lock once,
+  /// see [`intern_many`][crate::intern_many].
+  ///
+  /// Panics if the memory needed to store the new strings can't be
+  /// allocated.
+  #[inline]
+  pub fn intern_many<I>(&self, strs: I) -> Vec<PoolStr<'_>>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    let istrs = self.intern_many_istrs(strs);
+    istrs.into_iter().map(PoolStr::new).collect()
+  }
+
+  /// Intern a batch of strings, appending the [`PoolStr`]s to `out` in the
+  /// same order
+  ///
+  /// See [`Interner::intern_many`].
+  #[inline]
+  pub fn intern_many_into<'i, I, E>(&'i self, strs: I, out: &mut E)
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    E: Extend<PoolStr<'i>>,
+  {
+    out.extend(self.intern_many(strs));
+  }
+
+  /// Locklessly find an extant [`PoolStr`] corresponding to the string given,
+  /// if one exists
+  ///
+  /// Call this to find out if a string has already been interned, without
+  /// newly interning it if not.
+  #[inline]
+  pub fn get_interned(&self, s: &str) -> Option<PoolStr<'_>> {
+    self.get_interned_istr(s).map(PoolStr::new)
+  }
+
+  /// Locklessly find the [`PoolStr`] with the given id, if one exists
+  ///
+  /// See [`PoolStr::id`].
+  #[inline]
+  pub fn resolve(&self, id: u32) -> Option<PoolStr<'_>> {
+    let id = ::core::num::NonZeroU32::new(id)?;
+    self.resolve_sym(id).map(PoolStr::new)
+  }
+
+  /// Create a collection of all the strings currently interned in this pool
+  ///
+  /// The order of the items in the collection may not be stable.
+  #[inline]
+  pub fn collect_interned_strings<'i, B>(&'i self) -> B
+  where
+    B: ::core::iter::FromIterator<PoolStr<'i>>,
+  {
+    let istrs: Vec<IStr> = self.collect_istrs();
+    B::from_iter(istrs.into_iter().map(PoolStr::new))
+  }
+}
+
+// # reference types & conversion
+
+impl Deref for PoolStr<'_> {
+  type Target = str;
+
+  #[inline]
+  fn deref(&self) -> &str {
+    self.istr.as_str()
+  }
+}
+
+impl AsRef<str> for PoolStr<'_> {
+  #[inline]
+  fn as_ref(&self) -> &str {
+    self.istr.as_str()
+  }
+}
+
+impl Borrow<str> for PoolStr<'_> {
+  #[inline]
+  fn borrow(&self) -> &str {
+    self.istr.as_str()
+  }
+}
+
+impl<'i> PoolStr<'i> {
+  /// get the underlying `&str`
+  #[inline]
+  pub fn as_str(&self) -> &'i str {
+    self.istr.as_str()
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`]
+  ///
+  /// # Panics
+  ///
+  /// Panics if the string contains an interior null byte, see
+  /// [`PoolStr::try_as_c_str`].
+  #[inline]
+  #[track_caller]
+  pub fn as_c_str(&self) -> &'i CStr {
+    self.istr.as_c_str()
+  }
+
+  /// zero-cost conversion to a null terminated [`CStr`], or an error if the
+  /// string contains an interior null byte
+  #[inline]
+  pub fn try_as_c_str(&self) -> Result<&'i CStr, NulError> {
+    self.istr.try_as_c_str()
+  }
+
+  /// The id of this string in its pool
+  ///
+  /// Strings are given ids counting up from 1 in the order they're interned,
+  /// the same way the global pool numbers its [`Sym`][crate::Sym]s. The id is
+  /// stored next to the string so this method call is free.
+  #[inline]
+  pub fn id(&self) -> u32 {
+    self.istr.sym().to_u32()
+  }
+
+  /// The [wyhash](https://crates.io/crates/wyhash) value of this string
+  ///
+  /// This value is cached next to the string by the interner so this method
+  /// call is free.
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    self.istr.wyhash()
+  }
+}
+
+impl<'i> From<PoolStr<'i>> for &'i str {
+  #[inline]
+  fn from(p: PoolStr<'i>) -> &'i str {
+    p.as_str()
+  }
+}
+
+impl From<PoolStr<'_>> for String {
+  #[inline]
+  fn from(p: PoolStr<'_>) -> String {
+    p.as_str().to_owned()
+  }
+}
+
+impl From<IStr> for PoolStr<'_> {
+  /// An [`IStr`] from the global pool lives forever, so it can be used as a
+  /// `PoolStr` of any lifetime
+  #[inline]
+  fn from(istr: IStr) -> Self {
+    PoolStr::new(istr)
+  }
+}
+
+impl Display for PoolStr<'_> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl Debug for PoolStr<'_> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_fmt(format_args!("PoolStr(\"{}\")", self.as_str()))
+  }
+}
+
+// # equality
+
+impl PartialEq for PoolStr<'_> {
+  /// fast [`PoolStr`] comparison (pointer equality test)
+  ///
+  /// note: `PoolStr`s from different pools never compare equal
+  #[inline]
+  fn eq(&self, rhs: &PoolStr<'_>) -> bool {
+    self.istr == rhs.istr
+  }
+}
+
+impl PartialEq<&str> for PoolStr<'_> {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &&str) -> bool {
+    self.as_str() == *other
+  }
+}
+impl PartialEq<PoolStr<'_>> for &str {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &PoolStr<'_>) -> bool {
+    *self == other.as_str()
+  }
+}
+
+impl PartialEq<String> for PoolStr<'_> {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &String) -> bool {
+    self.as_str() == other
+  }
+}
+impl PartialEq<PoolStr<'_>> for String {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &PoolStr<'_>) -> bool {
+    self == other.as_str()
+  }
+}
+
+// # hashing
+
+impl Hash for PoolStr<'_> {
+  /// This feeds the underlying &str into the hasher
+  #[inline]
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.as_str().hash(state);
+  }
+}
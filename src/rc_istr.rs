@@ -0,0 +1,411 @@
+use {
+  crate::{intern, interner::WYHASH_SEED, IStr},
+  ::core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    convert::AsRef,
+    fmt::{self, Debug, Display},
+    hash::Hash,
+    ops::Deref,
+    ptr::NonNull,
+    slice, str,
+    sync::atomic::{self, AtomicUsize},
+  },
+  ::hashbrown::HashTable,
+  ::parking_lot::RwLock,
+  ::std::{
+    alloc::{self, Layout},
+    process,
+  },
+  ::wyhash::wyhash,
+};
+
+/// The pool of collectible strings
+///
+/// Readers hold the read lock while they look up a string & take a reference
+/// to it, and a string is only removed while holding the write lock, so a
+/// string can't be found after its count has dropped to zero.
+static RC_POOL: RwLock<HashTable<Record>> = RwLock::new(HashTable::new());
+
+/// A reference counted, collectible interned string
+///
+/// An `RcIStr` works like an [`IStr`], but it lives in a separate pool where
+/// each string is counted, and freed once the last `RcIStr` referring to it
+/// is dropped. This makes it suitable for short-lived strings, such as ones
+/// received over the network, which would otherwise grow the pool forever.
+///
+/// Cloning or dropping an `RcIStr` updates an atomic count, so it's a little
+/// more expensive to pass around than an [`IStr`]. Strings that are needed
+/// for the life of the program, such as a static vocabulary, are best kept as
+/// permanent [`IStr`]s; an `RcIStr` can be promoted with
+/// [`RcIStr::to_istr`].
+///
+/// ```rust
+/// # use sinter::RcIStr;
+/// let a = RcIStr::new("tag");
+/// let a2 = RcIStr::new("tag");
+/// assert!(a == a2);
+/// assert_eq!(RcIStr::strong_count(&a), 2);
+///
+/// drop((a, a2));
+/// // the string has been freed
+/// assert_eq!(RcIStr::get_interned("tag"), None);
+/// ```
+pub struct RcIStr(Record);
+
+/// A pointer to a collectible string's record, which doesn't hold a reference
+#[derive(Copy, Clone)]
+struct Record(NonNull<Header>);
+
+// safety: the count is atomic, and the rest of the record is immutable
+unsafe impl Send for Record {}
+unsafe impl Sync for Record {}
+
+/// The header of each collectible string, which is followed by the bytes of
+/// the string
+#[repr(C)]
+struct Header {
+  count: AtomicUsize,
+  wyhash: u64,
+  len: usize,
+}
+
+/// The largest count, past which we abort rather than risk overflowing
+const MAX_COUNT: usize = isize::MAX as usize;
+
+impl RcIStr {
+  /// Intern a new collectible string, or return another reference to the
+  /// extant [`RcIStr`] if one exists
+  ///
+  /// This takes a shared lock to find an extant string, or an exclusive lock
+  /// to add a new one.
+  #[inline]
+  pub fn new(s: &str) -> Self {
+    let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+    if let Some(rc) = Self::get_interned_with_wyhash(s, s_wyhash) {
+      return rc;
+    }
+
+    let mut pool = RC_POOL.write();
+    // note: another thread may have added the string before we took the lock
+    if let Some(&record) = pool.find(s_wyhash, |r| r.as_str() == s) {
+      // safety: we hold the lock, so the record is still in the pool
+      return unsafe { RcIStr::from_record(record) };
+    }
+    let record = Record::alloc(s, s_wyhash);
+    pool.insert_unique(s_wyhash, record, |r| r.header().wyhash);
+    RcIStr(record)
+  }
+
+  /// Find an extant [`RcIStr`] corresponding to the string given, if one
+  /// exists
+  #[inline]
+  pub fn get_interned(s: &str) -> Option<Self> {
+    Self::get_interned_with_wyhash(s, wyhash(s.as_bytes(), WYHASH_SEED))
+  }
+
+  #[inline]
+  fn get_interned_with_wyhash(s: &str, s_wyhash: u64) -> Option<Self> {
+    let pool = RC_POOL.read();
+    let &record = pool.find(s_wyhash, |r| r.as_str() == s)?;
+    // safety: we hold the lock, so the record is still in the pool
+    Some(unsafe { RcIStr::from_record(record) })
+  }
+
+  /// Take a new reference to a record in the pool
+  ///
+  /// # Safety
+  ///
+  /// - must be called while holding `RC_POOL`'s lock, & the record must be in
+  ///   the pool. Records are only removed while holding the write lock, after
+  ///   their count has dropped to zero, so the count can't be zero here.
+  #[inline]
+  unsafe fn from_record(record: Record) -> Self {
+    let rc = RcIStr(record);
+    ::core::mem::forget(rc.clone());
+    rc
+  }
+
+  /// The number of collectible strings currently in the pool
+  #[inline]
+  pub fn pool_len() -> usize {
+    RC_POOL.read().len()
+  }
+
+  /// The number of `RcIStr`s referring to this string
+  #[inline]
+  pub fn strong_count(this: &Self) -> usize {
+    this.0.header().count.load(atomic::Ordering::Acquire)
+  }
+
+  /// get the underlying `&str`
+  ///
+  /// The `&str` borrows from the `RcIStr`, since the string is freed once
+  /// every `RcIStr` referring to it has been dropped.
+  #[inline]
+  pub fn as_str(&self) -> &str {
+    self.0.as_str()
+  }
+
+  /// The [wyhash](https://crates.io/crates/wyhash) value of this string
+  ///
+  /// This value is cached next to the string so this method call is free.
+  /// It's the same as the [`IStr::wyhash`] of the equivalent [`IStr`].
+  #[inline]
+  pub fn wyhash(&self) -> u64 {
+    self.0.header().wyhash
+  }
+
+  /// Intern the string in the permanent pool
+  ///
+  /// The [`IStr`] is never freed, regardless of what happens to the
+  /// `RcIStr`.
+  #[inline]
+  pub fn to_istr(&self) -> IStr {
+    intern(self.as_str())
+  }
+}
+
+impl Record {
+  #[inline]
+  fn header(&self) -> &Header {
+    // safety: records are only used while they're alive
+    unsafe { self.0.as_ref() }
+  }
+
+  #[inline]
+  fn as_str(&self) -> &str {
+    // safety: the record stores the length of the string, which is valid
+    // utf-8, directly after the header
+    unsafe {
+      let ptr = self.0.as_ptr().add(1) as *const u8;
+      str::from_utf8_unchecked(slice::from_raw_parts(ptr, self.header().len))
+    }
+  }
+
+  /// The layout of the record for a string of length `len`
+  #[inline]
+  fn layout(len: usize) -> Layout {
+    let bytes = Layout::array::<u8>(len).unwrap();
+    Layout::new::<Header>().extend(bytes).unwrap().0
+  }
+
+  /// Allocate a new record for `s` with a count of one
+  fn alloc(s: &str, s_wyhash: u64) -> Self {
+    let layout = Self::layout(s.len());
+    // safety: the layout isn't zero sized, since the header isn't
+    let ptr = unsafe { alloc::alloc(layout) } as *mut Header;
+    let Some(header) = NonNull::new(ptr) else {
+      alloc::handle_alloc_error(layout);
+    };
+    // safety: the memory was just allocated with room for the header,
+    // followed by the bytes
+    unsafe {
+      header.write(Header {
+        count: AtomicUsize::new(1),
+        wyhash: s_wyhash,
+        len: s.len(),
+      });
+      let bytes = header.as_ptr().add(1) as *mut u8;
+      bytes.copy_from_nonoverlapping(s.as_ptr(), s.len());
+    }
+    Record(header)
+  }
+}
+
+impl Clone for RcIStr {
+  #[inline]
+  fn clone(&self) -> Self {
+    let old = self
+      .0
+      .header()
+      .count
+      .fetch_add(1, atomic::Ordering::Relaxed);
+    if old > MAX_COUNT {
+      process::abort();
+    }
+    RcIStr(self.0)
+  }
+}
+
+impl Drop for RcIStr {
+  #[inline]
+  fn drop(&mut self) {
+    let count = &self.0.header().count;
+    // decrement without the lock, unless this might be the last reference
+    let mut old = count.load(atomic::Ordering::Relaxed);
+    while old > 1 {
+      match count.compare_exchange_weak(
+        old,
+        old - 1,
+        atomic::Ordering::Release,
+        atomic::Ordering::Relaxed,
+      ) {
+        Ok(_) => return,
+        Err(new) => old = new,
+      }
+    }
+
+    // note: readers only take new references while holding the read lock, so
+    // while we hold the write lock the count can only go down
+    let mut pool = RC_POOL.write();
+    if count.fetch_sub(1, atomic::Ordering::AcqRel) != 1 {
+      return;
+    }
+    let s_wyhash = self.wyhash();
+    let Ok(entry) = pool.find_entry(s_wyhash, |r| r.0 == self.0 .0) else {
+      unreachable!("a collectible string is missing from the pool");
+    };
+    entry.remove();
+    drop(pool);
+    let layout = Record::layout(self.0.header().len);
+    // safety: the count is zero & the record has been removed from the pool,
+    // so there are no other references to it
+    unsafe { alloc::dealloc(self.0 .0.as_ptr() as *mut u8, layout) };
+  }
+}
+
+// # constructors
+
+impl From<&str> for RcIStr {
+  #[inline]
+  fn from(s: &str) -> Self {
+    RcIStr::new(s)
+  }
+}
+
+impl From<String> for RcIStr {
+  #[inline]
+  fn from(s: String) -> Self {
+    RcIStr::new(&s)
+  }
+}
+
+impl From<&String> for RcIStr {
+  #[inline]
+  fn from(s: &String) -> Self {
+    RcIStr::new(s)
+  }
+}
+
+impl From<IStr> for RcIStr {
+  #[inline]
+  fn from(istr: IStr) -> Self {
+    RcIStr::new(istr.as_str())
+  }
+}
+
+// # reference types & conversion
+
+impl Deref for RcIStr {
+  type Target = str;
+
+  #[inline]
+  fn deref(&self) -> &str {
+    self.as_str()
+  }
+}
+
+impl AsRef<str> for RcIStr {
+  #[inline]
+  fn as_ref(&self) -> &str {
+    self.as_str()
+  }
+}
+
+impl Borrow<str> for RcIStr {
+  #[inline]
+  fn borrow(&self) -> &str {
+    self.as_str()
+  }
+}
+
+impl From<RcIStr> for String {
+  #[inline]
+  fn from(s: RcIStr) -> String {
+    s.as_str().to_owned()
+  }
+}
+
+impl Display for RcIStr {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl Debug for RcIStr {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "RcIStr({:?})", self.as_str())
+  }
+}
+
+// # equality
+
+impl PartialEq for RcIStr {
+  /// fast [`RcIStr`] comparison (pointer equality test)
+  #[inline]
+  fn eq(&self, rhs: &RcIStr) -> bool {
+    // it is sufficient to compare the pointers, because the pool never holds
+    // two distinct records with the same data
+    self.0 .0 == rhs.0 .0
+  }
+}
+
+impl Eq for RcIStr {}
+
+impl PartialOrd for RcIStr {
+  #[inline]
+  fn partial_cmp(&self, rhs: &RcIStr) -> Option<Ordering> {
+    Some(self.cmp(rhs))
+  }
+}
+
+impl Ord for RcIStr {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn cmp(&self, rhs: &RcIStr) -> Ordering {
+    self.as_str().cmp(rhs.as_str())
+  }
+}
+
+impl PartialEq<&str> for RcIStr {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &&str) -> bool {
+    self.as_str() == *other
+  }
+}
+impl PartialEq<RcIStr> for &str {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &RcIStr) -> bool {
+    *self == other.as_str()
+  }
+}
+
+impl PartialEq<IStr> for RcIStr {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &IStr) -> bool {
+    self.as_str() == other.as_str()
+  }
+}
+impl PartialEq<RcIStr> for IStr {
+  /// full (potentially slow) string comparison
+  #[inline]
+  fn eq(&self, other: &RcIStr) -> bool {
+    self.as_str() == other.as_str()
+  }
+}
+
+// # hashing
+
+impl Hash for RcIStr {
+  /// This feeds the underlying &str into the hasher
+  #[inline]
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.as_str().hash(state);
+  }
+}
@@ -0,0 +1,101 @@
+use {
+  crate::{
+    interner::{THE_INTERNER, WYHASH_SEED},
+    IStr, PoolStr, Sym,
+  },
+  ::core::fmt,
+  ::serde::{
+    de::{self, Deserialize, Deserializer, Visitor},
+    ser::{Serialize, Serializer},
+  },
+  ::wyhash::wyhash,
+};
+
+impl Serialize for IStr {
+  /// Serializes the string
+  #[inline]
+  fn serialize<S: Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.as_str())
+  }
+}
+
+impl Serialize for PoolStr<'_> {
+  /// Serializes the string
+  #[inline]
+  fn serialize<S: Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.as_str())
+  }
+}
+
+impl Serialize for Sym {
+  /// Serializes the string, since [`Sym`] numbers depend on the order strings
+  /// were interned in
+  #[inline]
+  fn serialize<S: Serializer>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.as_str())
+  }
+}
+
+impl<'de> Deserialize<'de> for IStr {
+  /// Deserializes a string and interns it
+  #[inline]
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_str(IStrVisitor)
+  }
+}
+
+impl<'de> Deserialize<'de> for Sym {
+  /// Deserializes a string and interns it
+  #[inline]
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    IStr::deserialize(deserializer).map(|istr| istr.sym())
+  }
+}
+
+struct IStrVisitor;
+
+impl IStrVisitor {
+  /// Interns the string, failing the deserialization instead of panicking if
+  /// it's too long or can't be stored
+  #[inline]
+  fn intern<E: de::Error>(s: &str) -> Result<IStr, E> {
+    let s_wyhash = wyhash(s.as_bytes(), WYHASH_SEED);
+    THE_INTERNER
+      .try_intern_istr_with_wyhash(s, s_wyhash)
+      .map_err(E::custom)
+  }
+}
+
+impl Visitor<'_> for IStrVisitor {
+  type Value = IStr;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("a string")
+  }
+
+  #[inline]
+  fn visit_str<E: de::Error>(self, s: &str) -> Result<IStr, E> {
+    Self::intern(s)
+  }
+
+  #[inline]
+  fn visit_bytes<E: de::Error>(self, b: &[u8]) -> Result<IStr, E> {
+    match ::core::str::from_utf8(b) {
+      Ok(s) => Self::intern(s),
+      Err(_) => Err(E::invalid_value(de::Unexpected::Bytes(b), &self)),
+    }
+  }
+}
@@ -0,0 +1,317 @@
+//! Save the whole pool to a file, and load it into a fresh process
+//!
+//! A snapshot stores every interned string & byte string along with its
+//! [`Sym`][crate::Sym] number & cached wyhash, in the order they were
+//! interned. Loading a snapshot into an empty pool reproduces the same `Sym`
+//! numbers, even if some were missing. The hashes are checked against the
+//! strings when loading.
+//!
+//! ```rust
+//! # use sinter::{intern, snapshot, Interner};
+//! intern("snapshot");
+//! let mut file = Vec::new();
+//! snapshot::save(&mut file).unwrap();
+//!
+//! let fresh = Interner::new();
+//! fresh.load_snapshot(file.as_slice()).unwrap();
+//! assert!(fresh.get_interned("snapshot").is_some());
+//! ```
+//!
+//! The format is little-endian throughout:
+//!
+//! ```text
+//! [magic: 8 bytes][version: u32][count: u64]
+//! count * [sym: u32][len: u32][wyhash: u64][bytes: len bytes]
+//! [checksum: u64]
+//! ```
+//!
+//! where the checksum is the 64-bit FNV-1a hash of everything preceding it.
+
+use {
+  crate::{
+    interner::{
+      max_sym, InternError, Interner, MAX_LEN, THE_INTERNER, WYHASH_SEED,
+    },
+    IBytes,
+  },
+  ::core::{
+    fmt::{self, Display},
+    num::NonZeroU32,
+  },
+  ::std::io::{self, Read, Write},
+  ::wyhash::wyhash,
+};
+
+/// Identifies a file as a sinter snapshot
+const MAGIC: [u8; 8] = *b"SINTER\0\0";
+/// The version of the snapshot format. This must change whenever the format,
+/// the hash function, or the meaning of the records changes.
+///
+/// - 1: strings
+/// - 2: strings & byte strings that aren't valid utf-8
+/// - 3: each record starts with its `Sym`, so that a missing `Sym` doesn't
+///   renumber the rest
+const VERSION: u32 = 3;
+
+/// Save a snapshot of the global pool
+#[inline]
+pub fn save<W: Write>(w: &mut W) -> io::Result<()> {
+  save_syms(THE_INTERNER.syms_in_order(), w)
+}
+
+/// Load a snapshot into the global pool
+///
+/// Strings in the snapshot that have already been interned are skipped. If
+/// the pool is empty then the strings will be given the same
+/// [`Sym`][crate::Sym] numbers they had when the snapshot was saved.
+///
+/// Nothing is interned unless the whole snapshot is valid. If the memory
+/// needed can't be allocated, or would take the pool over its byte budget,
+/// [`Error::Intern`] is returned, and the strings loaded before the failure
+/// are kept, since a pool can't forget strings.
+#[inline]
+pub fn load<R: Read>(r: R) -> Result<(), Error> {
+  load_into(&THE_INTERNER, r)
+}
+
+impl Interner {
+  /// Save a snapshot of this pool, see [`snapshot::save`][save]
+  #[inline]
+  pub fn save_snapshot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+    save_syms(self.syms_in_order(), w)
+  }
+
+  /// Load a snapshot into this pool, see [`snapshot::load`][load]
+  #[inline]
+  pub fn load_snapshot<R: Read>(&self, r: R) -> Result<(), Error> {
+    load_into(self, r)
+  }
+}
+
+fn save_syms<W: Write>(
+  syms: impl Iterator<Item = (NonZeroU32, IBytes)>,
+  w: &mut W,
+) -> io::Result<()> {
+  let mut w = ChecksumWriter {
+    inner: io::BufWriter::new(w),
+    checksum: Fnv1a::new(),
+  };
+  let syms = syms.collect::<Vec<_>>();
+  w.write_all(&MAGIC)?;
+  w.write_all(&VERSION.to_le_bytes())?;
+  w.write_all(&(syms.len() as u64).to_le_bytes())?;
+  for (sym, b) in syms {
+    w.write_all(&sym.get().to_le_bytes())?;
+    // note: the Interner only stores strings up to 2^30 - 1 bytes long
+    w.write_all(&(b.len() as u32).to_le_bytes())?;
+    w.write_all(&b.wyhash().to_le_bytes())?;
+    w.write_all(b.as_bytes())?;
+  }
+  let checksum = w.checksum.0;
+  w.inner.write_all(&checksum.to_le_bytes())?;
+  w.inner.flush()
+}
+
+fn load_into<R: Read>(interner: &Interner, mut r: R) -> Result<(), Error> {
+  let mut bytes = Vec::new();
+  r.read_to_end(&mut bytes)?;
+
+  // check the header & checksum before interning anything
+  let mut cursor = Cursor(&bytes);
+  if cursor.take(MAGIC.len())? != MAGIC {
+    return Err(Error::BadMagic);
+  }
+  let version = u32::from_le_bytes(cursor.take_array()?);
+  if version != VERSION {
+    return Err(Error::UnsupportedVersion(version));
+  }
+  // note: the count and the checksum are both u64s
+  if cursor.0.len() < 8 + 8 {
+    return Err(Error::UnexpectedEof);
+  }
+  let (body, checksum) = bytes.split_at(bytes.len() - 8);
+  let mut fnv = Fnv1a::new();
+  fnv.write(body);
+  if fnv.0 != u64::from_le_bytes(checksum.try_into().unwrap()) {
+    return Err(Error::ChecksumMismatch);
+  }
+
+  let mut cursor = Cursor(&body[MAGIC.len() + 4..]);
+  let count = u64::from_le_bytes(cursor.take_array()?);
+  let mut syms = Vec::new();
+  let mut records = Vec::new();
+  for _ in 0..count {
+    let sym = u32::from_le_bytes(cursor.take_array()?);
+    if sym <= syms.last().copied().unwrap_or(0) {
+      return Err(Error::SymOutOfOrder);
+    }
+    syms.push(sym);
+    let len = u32::from_le_bytes(cursor.take_array()?);
+    let s_wyhash = u64::from_le_bytes(cursor.take_array()?);
+    let s = cursor.take(len as usize)?;
+    if s.len() > MAX_LEN {
+      return Err(Error::TooLong);
+    }
+    if s_wyhash != wyhash(s, WYHASH_SEED) {
+      return Err(Error::HashMismatch);
+    }
+    records.push((s, s_wyhash));
+  }
+  if !cursor.0.is_empty() {
+    return Err(Error::TrailingBytes);
+  }
+  // note: the gaps are bounded like those of a file-backed pool, so that
+  // skipping them can't reserve an unbounded number of `Sym`s
+  if syms
+    .last()
+    .is_some_and(|&last| last > max_sym(records.len()))
+  {
+    return Err(Error::SymOutOfRange);
+  }
+
+  // note: if the pool is empty, any `Sym`s missing from the snapshot are
+  // skipped, so that the strings after them keep their numbers. Each run of
+  // consecutive `Sym`s is interned as one batch
+  let reproduce_syms = interner.syms_reserved() == 0;
+  let mut start = 0;
+  while start < records.len() {
+    let mut end = records.len();
+    if reproduce_syms {
+      interner.skip_syms_through(syms[start] - 1);
+      end = start + 1;
+      while end < records.len() && syms[end] == syms[end - 1] + 1 {
+        end += 1;
+      }
+    }
+    interner
+      .try_intern_many_ibytes_with_wyhash(&records[start..end], false)?;
+    start = end;
+  }
+  Ok(())
+}
+
+/// The error returned when a snapshot can't be loaded
+#[derive(Debug)]
+pub enum Error {
+  /// Reading the snapshot failed
+  Io(io::Error),
+  /// The file is not a snapshot
+  BadMagic,
+  /// The snapshot was saved in a different format, by an incompatible version
+  /// of this crate
+  UnsupportedVersion(u32),
+  /// The snapshot is corrupt
+  ChecksumMismatch,
+  /// The snapshot ended unexpectedly
+  UnexpectedEof,
+  /// The snapshot continues after the last string
+  TrailingBytes,
+  /// The strings' [`Sym`][crate::Sym] numbers aren't in ascending order
+  SymOutOfOrder,
+  /// The strings' [`Sym`][crate::Sym] numbers leave more gaps than a pool
+  /// can, or are larger than any the pool gives out
+  SymOutOfRange,
+  /// A string's stored hash doesn't match the string
+  HashMismatch,
+  /// A string in the snapshot is too long for the Interner to store
+  TooLong,
+  /// The pool couldn't store the snapshot's strings
+  Intern(InternError),
+}
+
+impl Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Error::Io(e) => write!(f, "failed to read snapshot: {e}"),
+      Error::BadMagic => f.write_str("not a sinter snapshot"),
+      Error::UnsupportedVersion(v) => write!(
+        f,
+        "unsupported snapshot version {v} (expected version {VERSION})"
+      ),
+      Error::ChecksumMismatch => f.write_str("snapshot checksum mismatch"),
+      Error::UnexpectedEof => f.write_str("snapshot is truncated"),
+      Error::TrailingBytes => f.write_str("snapshot has trailing bytes"),
+      Error::SymOutOfOrder => f.write_str("snapshot syms are out of order"),
+      Error::SymOutOfRange => f.write_str("snapshot syms are out of range"),
+      Error::HashMismatch => f.write_str("snapshot string hash mismatch"),
+      Error::TooLong => {
+        f.write_str("snapshot contains a string too long to intern")
+      },
+      Error::Intern(e) => write!(f, "failed to load snapshot: {e}"),
+    }
+  }
+}
+
+impl ::std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+    match self {
+      Error::Io(e) => Some(e),
+      Error::Intern(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for Error {
+  #[inline]
+  fn from(e: io::Error) -> Self {
+    Error::Io(e)
+  }
+}
+
+impl From<InternError> for Error {
+  #[inline]
+  fn from(e: InternError) -> Self {
+    Error::Intern(e)
+  }
+}
+
+/// 64-bit FNV-1a, used as the snapshot checksum
+struct Fnv1a(u64);
+
+impl Fnv1a {
+  #[inline]
+  fn new() -> Self {
+    Fnv1a(0xcbf2_9ce4_8422_2325)
+  }
+
+  #[inline]
+  fn write(&mut self, bytes: &[u8]) {
+    for &byte in bytes {
+      self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+  }
+}
+
+/// Checksums everything written through it
+struct ChecksumWriter<W> {
+  inner: W,
+  checksum: Fnv1a,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+  #[inline]
+  fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+    self.checksum.write(bytes);
+    self.inner.write_all(bytes)
+  }
+}
+
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+  #[inline]
+  fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+    if self.0.len() < n {
+      return Err(Error::UnexpectedEof);
+    }
+    let (head, tail) = self.0.split_at(n);
+    self.0 = tail;
+    Ok(head)
+  }
+
+  #[inline]
+  fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+    Ok(self.take(N)?.try_into().unwrap())
+  }
+}
@@ -0,0 +1,372 @@
+use {
+  crate::{
+    const_wyhash::wyhash,
+    interner::{
+      LEN_HAS_NUL, MAX_LEN, SIZE_OF_HEADER, STATIC_SYM, WYHASH_SEED,
+    },
+    IStr, Sym,
+  },
+  ::core::{
+    fmt::{self, Debug, Display},
+    mem::size_of,
+    num::NonZeroU32,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+  },
+};
+
+/// Define a table of strings stored in a `static`, with an [`IStr`] constant
+/// for each of them
+///
+/// The strings are laid out at compile time exactly the way the Interner
+/// stores strings it interns at runtime, so creating the [`IStr`] constants
+/// costs nothing, and neither does comparing them. Once the table is
+/// registered with the global pool, using [`StaticTable::register`],
+/// interning any of its strings returns the corresponding constant.
+///
+/// ```rust
+/// # use sinter::{intern, static_table, IStr};
+/// static_table! {
+///   /// Keywords of our language
+///   pub static KEYWORDS;
+///   pub const KW_FN: IStr = "fn";
+///   pub const KW_LET: IStr = "let";
+/// }
+///
+/// KEYWORDS.register().unwrap();
+/// assert!(intern("fn") == KW_FN);
+/// assert!(intern("let") == KW_LET);
+/// assert_eq!(KW_LET.sym().to_u32(), KW_FN.sym().to_u32() + 1);
+/// ```
+///
+/// Registering the table gives its strings the next [`Sym`][crate::Sym]
+/// numbers of the global pool, in the order they're listed. Until then they
+/// have no `Sym`, and [`IStr::sym`] panics. Any number of tables can be
+/// registered, as long as none of their strings have been interned already.
+///
+/// Registering isn't free: it takes the lock of each of the pool's shards
+/// once, waits for their readers, and allocates room for the table's
+/// strings in the pool's hash tables & `Sym` index. The strings themselves
+/// are never copied.
+#[macro_export]
+macro_rules! static_table {
+  (
+    $(#[$table_meta:meta])*
+    $table_vis:vis static $table:ident;
+    $(
+      $(#[$meta:meta])*
+      $vis:vis const $name:ident: IStr = $s:literal;
+    )*
+  ) => {
+    #[doc(hidden)]
+    #[allow(non_snake_case)]
+    mod $table {
+      #[allow(non_camel_case_types)]
+      pub(super) enum Index {
+        $($name,)*
+      }
+      pub(super) const STRS: &[&str] = &[$($s),*];
+      pub(super) static DATA: $crate::__private::StaticTableData<
+        { STRS.len() },
+        { $crate::__private::static_table_size(STRS) },
+      > = $crate::__private::StaticTableData::new(
+        $crate::__private::static_table_bytes(STRS),
+      );
+    }
+
+    $(#[$table_meta])*
+    $table_vis static $table: $crate::StaticTable = $crate::StaticTable::__new(
+      &$table::DATA,
+      &$crate::__private::static_table_offsets::<{ $table::STRS.len() }>(
+        $table::STRS,
+      ),
+    );
+
+    $(
+      $(#[$meta])*
+      $vis const $name: $crate::IStr = $table.get($table::Index::$name as usize);
+    )*
+  };
+}
+
+/// The memory of a [`StaticTable`], defined by the [`static_table!`] macro
+///
+/// note: the strings' `Sym`s aren't known until the table is registered, so
+/// they're stored in `syms`, which is writable, rather than in the strings'
+/// headers. Each header's sym word is `STATIC_SYM` plus the distance back
+/// from the header to the string's entry of `syms`
+#[doc(hidden)]
+#[repr(C)]
+pub struct StaticTableData<const COUNT: usize, const SIZE: usize> {
+  /// whether the table has been registered with a pool
+  registered: AtomicBool,
+  /// the `Sym` of each string, or zero until the table is registered
+  syms: [AtomicU32; COUNT],
+  /// the strings, laid out as `[sym][len][wyhash][bytes][NUL]`
+  bytes: [u8; SIZE],
+}
+
+impl<const COUNT: usize, const SIZE: usize> StaticTableData<COUNT, SIZE> {
+  pub const fn new(bytes: [u8; SIZE]) -> Self {
+    StaticTableData {
+      registered: AtomicBool::new(false),
+      syms: [const { AtomicU32::new(0) }; COUNT],
+      bytes,
+    }
+  }
+}
+
+/// A table of strings stored in static memory, defined with the
+/// [`static_table!`] macro
+pub struct StaticTable {
+  /// see `StaticTableData`
+  ///
+  /// note: these all point into the same `StaticTableData`, so that a
+  /// string's header can be followed back to its entry of `syms`
+  registered: *const AtomicBool,
+  syms: *const AtomicU32,
+  bytes: *const u8,
+  /// the size of `bytes`
+  size: usize,
+  /// the offsets of the start of each string's header in `bytes`
+  offsets: &'static [u32],
+}
+
+// safety: the table only points into a `StaticTableData`, which is `Sync`
+unsafe impl Send for StaticTable {}
+unsafe impl Sync for StaticTable {}
+
+impl StaticTable {
+  #[doc(hidden)]
+  pub const fn __new<const COUNT: usize, const SIZE: usize>(
+    data: &'static StaticTableData<COUNT, SIZE>,
+    offsets: &'static [u32; COUNT],
+  ) -> Self {
+    let data = data as *const StaticTableData<COUNT, SIZE>;
+    StaticTable {
+      // safety: the fields are in bounds of `data`
+      registered: unsafe { ptr::addr_of!((*data).registered) },
+      syms: unsafe { ptr::addr_of!((*data).syms) }.cast(),
+      bytes: unsafe { ptr::addr_of!((*data).bytes) }.cast(),
+      size: SIZE,
+      offsets,
+    }
+  }
+
+  /// Get the `index`th string in the table
+  ///
+  /// Panics if `index` is out of bounds
+  #[inline]
+  pub const fn get(&self, index: usize) -> IStr {
+    let str_index = self.offsets[index] as usize + SIZE_OF_HEADER;
+    assert!(str_index < self.size);
+    // safety: we just checked the index is in bounds
+    let ptr = unsafe { self.bytes.add(str_index) };
+    // safety: a pointer into a static is never null
+    IStr(unsafe { NonNull::new_unchecked(ptr as *mut u8) })
+  }
+
+  /// The number of strings in the table
+  #[inline]
+  pub const fn len(&self) -> usize {
+    self.offsets.len()
+  }
+
+  /// Returns `true` if the table contains no strings
+  #[inline]
+  pub const fn is_empty(&self) -> bool {
+    self.offsets.is_empty()
+  }
+
+  /// Iterate over the strings in the table, in order
+  #[inline]
+  pub fn iter(&self) -> impl ExactSizeIterator<Item = IStr> + '_ {
+    (0..self.len()).map(|i| self.get(i))
+  }
+
+  /// Register the strings in this table with the global pool, so that
+  /// interning any of them returns the [`IStr`] from this table
+  ///
+  /// This must be called before any of the table's strings are interned,
+  /// and only once. It takes the pool's locks & allocates, see
+  /// [`static_table!`].
+  #[inline]
+  pub fn register(&'static self) -> Result<(), RegisterError> {
+    crate::interner::THE_INTERNER.register_static_table(self)
+  }
+
+  /// Whether the table has been registered with a pool
+  #[inline]
+  pub(crate) fn is_registered(&self) -> bool {
+    // safety: the flag is in a static
+    unsafe { &*self.registered }.load(Ordering::Relaxed)
+  }
+
+  /// Mark the table as registered, returning `false` if it already was
+  #[inline]
+  pub(crate) fn claim(&self) -> bool {
+    // safety: the flag is in a static
+    !unsafe { &*self.registered }.swap(true, Ordering::Relaxed)
+  }
+
+  /// Give the `index`th string in the table its `Sym`
+  ///
+  /// note: the `Sym` must already be readable from the pool, since any
+  /// thread holding the string's `IStr` may read it straight away
+  #[inline]
+  pub(crate) fn set_sym(&self, index: usize, sym: NonZeroU32) {
+    assert!(index < self.len());
+    // safety: we just checked the index is in bounds
+    unsafe { &*self.syms.add(index) }.store(sym.get(), Ordering::Release);
+  }
+}
+
+/// The `Sym` of a string in a [`StaticTable`], whose header starts at
+/// `header` with the sym word `sym_word`
+///
+/// Panics if the table hasn't been registered.
+///
+/// # Safety
+///
+/// - `header` must point to the header of a string in a `StaticTable`
+#[inline]
+pub(crate) unsafe fn static_sym(header: *const u8, sym_word: u32) -> Sym {
+  debug_assert!(sym_word & STATIC_SYM != 0);
+  // safety: the header's sym word is the distance back to the string's
+  // entry of the table's `syms`
+  let sym = unsafe {
+    &*(header.sub((sym_word & !STATIC_SYM) as usize) as *const AtomicU32)
+  };
+  match NonZeroU32::new(sym.load(Ordering::Acquire)) {
+    Some(sym) => Sym(sym),
+    None => {
+      panic!("a static table's strings have no Sym until it's registered")
+    },
+  }
+}
+
+impl Debug for StaticTable {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_list().entries(self.iter()).finish()
+  }
+}
+
+/// The error returned when a [`StaticTable`] can't be registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterError {
+  /// The table has already been registered
+  AlreadyRegistered,
+  /// The pool already contains one of the table's strings
+  AlreadyInterned(&'static str),
+  /// The table contains the same string more than once
+  Duplicate(&'static str),
+}
+
+impl Display for RegisterError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RegisterError::AlreadyRegistered => {
+        f.write_str("the static table has already been registered")
+      },
+      RegisterError::AlreadyInterned(s) => {
+        write!(f, "the pool already contains {s:?} from the static table")
+      },
+      RegisterError::Duplicate(s) => {
+        write!(f, "the static table contains {s:?} more than once")
+      },
+    }
+  }
+}
+
+impl ::std::error::Error for RegisterError {}
+
+// # compile time table construction, used by the `static_table!` macro
+
+/// The number of bytes needed to store all of the `strs` in a table
+pub const fn static_table_size(strs: &[&str]) -> usize {
+  let mut size = 0;
+  let mut i = 0;
+  while i < strs.len() {
+    // note: we leave room for the trailing null byte
+    size += SIZE_OF_HEADER + strs[i].len() + 1;
+    i += 1;
+  }
+  size
+}
+
+/// The offsets of the start of each string's header in the table
+pub const fn static_table_offsets<const N: usize>(strs: &[&str]) -> [u32; N] {
+  let mut offsets = [0; N];
+  let mut offset = 0;
+  let mut i = 0;
+  while i < N {
+    assert!(
+      offset <= u32::MAX as usize,
+      "static tables must be smaller than 4GiB"
+    );
+    offsets[i] = offset as u32;
+    offset += SIZE_OF_HEADER + strs[i].len() + 1;
+    i += 1;
+  }
+  offsets
+}
+
+/// Lay out the `strs` the same way the Interner does:
+/// `[sym][len][wyhash][bytes][NUL]`
+///
+/// The sym word of each string is `STATIC_SYM` plus the distance back to its
+/// entry of the `syms` preceding the bytes in the `StaticTableData`.
+pub const fn static_table_bytes<const N: usize>(strs: &[&str]) -> [u8; N] {
+  let mut bytes = [0; N];
+  let mut index = 0;
+  let mut i = 0;
+  while i < strs.len() {
+    let s = strs[i].as_bytes();
+    assert!(
+      s.len() <= MAX_LEN,
+      "The Interner can't store strings longer than 2^30 - 1 bytes"
+    );
+    let mut len_word = s.len() as u32;
+    if contains_nul(s) {
+      len_word |= LEN_HAS_NUL;
+    }
+    // note: the `syms` are directly before the bytes
+    let sym_distance = (strs.len() - i) * size_of::<AtomicU32>() + index;
+    assert!(
+      sym_distance < STATIC_SYM as usize,
+      "static tables must be smaller than 2GiB"
+    );
+    let sym_word = STATIC_SYM | sym_distance as u32;
+    index = copy(&mut bytes, index, &sym_word.to_ne_bytes());
+    index = copy(&mut bytes, index, &len_word.to_ne_bytes());
+    index = copy(&mut bytes, index, &wyhash(s, WYHASH_SEED).to_ne_bytes());
+    index = copy(&mut bytes, index, s);
+    // note: the trailing null byte is already zeroed
+    index += 1;
+    i += 1;
+  }
+  assert!(index == N);
+  return bytes;
+
+  /// copy `src` into `dst` at `index`, returning the index after it
+  const fn copy(dst: &mut [u8], mut index: usize, src: &[u8]) -> usize {
+    let mut i = 0;
+    while i < src.len() {
+      dst[index] = src[i];
+      index += 1;
+      i += 1;
+    }
+    index
+  }
+
+  const fn contains_nul(s: &[u8]) -> bool {
+    let mut i = 0;
+    while i < s.len() {
+      if s[i] == 0 {
+        return true;
+      }
+      i += 1;
+    }
+    false
+  }
+}
@@ -0,0 +1,149 @@
+//! Statistics about the size & memory usage of a pool
+
+#[cfg(feature = "counters")]
+use ::core::sync::atomic::{AtomicU64, Ordering};
+
+/// Gather statistics about the global pool's size & memory usage
+///
+/// See [`Interner::stats`][crate::Interner::stats].
+///
+/// ```rust
+/// # use sinter::{intern, stats};
+/// intern("stats");
+/// assert!(stats().strings > 0);
+/// ```
+#[inline]
+pub fn stats() -> Stats {
+  crate::interner::THE_INTERNER.stats()
+}
+
+/// Statistics about a pool, returned by [`stats`] &
+/// [`Interner::stats`][crate::Interner::stats]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+  /// The number of strings interned
+  pub strings: usize,
+  /// The total length in bytes of all the strings interned
+  pub payload_bytes: usize,
+  /// The capacity & usage of each page of memory the strings are stored in,
+  /// in order, shard by shard
+  pub pages: Vec<PageStats>,
+  /// The bytes left unused at the end of each page when a string didn't fit
+  /// and the shard's next page was created
+  pub wasted_bytes: usize,
+  /// The capacity of the hash tables readers are looking up strings in,
+  /// summed over the shards
+  pub id_map_capacity: usize,
+  /// The capacity of the writers' copies of the hash tables, summed over the
+  /// shards
+  pub id_map_mut_capacity: usize,
+  /// The number of bytes allocated for the pages, tables & sym index
+  ///
+  /// note: the size of the hash tables is estimated. This is the number
+  /// compared against the [byte budget][crate::Interner::set_byte_budget].
+  pub allocated_bytes: usize,
+  /// The number of threads with a registered epoch counter
+  ///
+  /// note: epoch counters are shared by all pools
+  pub thread_epochs: usize,
+}
+
+/// The capacity & usage of a page of memory, see [`Stats::pages`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageStats {
+  /// The shard of the pool the page belongs to
+  ///
+  /// A pool's strings are split between independent shards by their hash,
+  /// each with its own pages.
+  pub shard: usize,
+  /// The size of the page in bytes
+  pub capacity: usize,
+  /// The number of bytes used, including each string's header & trailing
+  /// null byte
+  pub used: usize,
+}
+
+/// Gather the counters of the work done by the global pool's write path
+///
+/// See [`Interner::counters`][crate::Interner::counters].
+#[cfg(feature = "counters")]
+#[inline]
+pub fn counters() -> Counters {
+  crate::interner::THE_INTERNER.counters()
+}
+
+/// Counters of the work done by a pool's write path, returned by [`counters`]
+/// & [`Interner::counters`][crate::Interner::counters]
+///
+/// The counters only ever increase, so the work done over some period is the
+/// difference between two snapshots.
+#[cfg(feature = "counters")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+  /// Calls to intern that found the string without taking the lock
+  ///
+  /// note: strings found in the global pool's
+  /// [thread cache](crate#cargo-features) aren't counted.
+  pub fast_path_hits: u64,
+  /// Calls to intern that inserted a new string
+  pub slow_path_inserts: u64,
+  /// Calls to intern that took the lock, then found the string was the one
+  /// most recently interned by another thread
+  pub pending_add_hits: u64,
+  /// Calls to intern that took the lock, then found the string in the
+  /// writer's copy of the hash table
+  pub id_map_mut_hits: u64,
+  /// The number of times the writer had to wait for the lock
+  pub lock_waits: u64,
+  /// The total time spent waiting for the lock, in nanoseconds
+  pub lock_wait_nanos: u64,
+  /// The number of times the writer spun or yielded waiting for readers to
+  /// finish with its copy of the hash table
+  pub epoch_wait_spins: u64,
+  /// The number of times the writer parked waiting for readers to finish with
+  /// its copy of the hash table, after spinning didn't see them finish
+  pub epoch_wait_parks: u64,
+}
+
+/// The Interner's counters, incremented with the `count!` macro
+#[cfg(feature = "counters")]
+pub(crate) struct AtomicCounters {
+  pub(crate) fast_path_hits: AtomicU64,
+  pub(crate) slow_path_inserts: AtomicU64,
+  pub(crate) pending_add_hits: AtomicU64,
+  pub(crate) id_map_mut_hits: AtomicU64,
+  pub(crate) lock_waits: AtomicU64,
+  pub(crate) lock_wait_nanos: AtomicU64,
+  pub(crate) epoch_wait_spins: AtomicU64,
+  pub(crate) epoch_wait_parks: AtomicU64,
+}
+
+#[cfg(feature = "counters")]
+impl AtomicCounters {
+  pub(crate) const fn new() -> Self {
+    AtomicCounters {
+      fast_path_hits: AtomicU64::new(0),
+      slow_path_inserts: AtomicU64::new(0),
+      pending_add_hits: AtomicU64::new(0),
+      id_map_mut_hits: AtomicU64::new(0),
+      lock_waits: AtomicU64::new(0),
+      lock_wait_nanos: AtomicU64::new(0),
+      epoch_wait_spins: AtomicU64::new(0),
+      epoch_wait_parks: AtomicU64::new(0),
+    }
+  }
+
+  #[inline]
+  pub(crate) fn load(&self) -> Counters {
+    Counters {
+      fast_path_hits: self.fast_path_hits.load(Ordering::Relaxed),
+      slow_path_inserts: self.slow_path_inserts.load(Ordering::Relaxed),
+      pending_add_hits: self.pending_add_hits.load(Ordering::Relaxed),
+      id_map_mut_hits: self.id_map_mut_hits.load(Ordering::Relaxed),
+      lock_waits: self.lock_waits.load(Ordering::Relaxed),
+      lock_wait_nanos: self.lock_wait_nanos.load(Ordering::Relaxed),
+      epoch_wait_spins: self.epoch_wait_spins.load(Ordering::Relaxed),
+      epoch_wait_parks: self.epoch_wait_parks.load(Ordering::Relaxed),
+    }
+  }
+}
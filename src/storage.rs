@@ -0,0 +1,193 @@
+//! Backends providing the memory for the Interner's pages
+
+use {
+  ::core::ptr::NonNull,
+  ::std::alloc::{self, Layout},
+};
+
+#[cfg(feature = "mmap")]
+use {
+  ::memmap2::MmapMut,
+  ::parking_lot::Mutex,
+  ::std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io,
+    path::{Path, PathBuf},
+  },
+};
+
+/// Where an Interner gets the memory for its pages from
+pub(crate) enum Storage {
+  /// pages are allocated on the heap, and freed when the Interner is dropped
+  Heap,
+  /// pages are memory-mapped files in a directory, which persist after the
+  /// Interner is dropped
+  #[cfg(feature = "mmap")]
+  Mmap(MmapStorage),
+}
+
+impl Storage {
+  /// Try to allocate `capacity` zeroed bytes for a new page
+  ///
+  /// note: the page is returned as a raw pointer, since strings borrow from
+  /// it while the rest of it is still being written to
+  #[inline]
+  pub(crate) fn try_alloc(&self, capacity: usize) -> Option<NonNull<[u8]>> {
+    match self {
+      Storage::Heap => {
+        let layout = Layout::array::<u8>(capacity).ok()?;
+        // safety: pages are never zero sized
+        let mem = NonNull::new(unsafe { alloc::alloc_zeroed(layout) })?;
+        Some(NonNull::slice_from_raw_parts(mem, capacity))
+      },
+      #[cfg(feature = "mmap")]
+      Storage::Mmap(mmap) => mmap.alloc(capacity).ok(),
+    }
+  }
+
+  /// Free the memory of a page
+  ///
+  /// # Safety
+  ///
+  /// - `mem` must have been allocated by this `Storage`, and there must be no
+  ///   remaining references into it
+  #[inline]
+  pub(crate) unsafe fn free(&self, mem: NonNull<[u8]>) {
+    match self {
+      Storage::Heap => {
+        // safety: the layout is the one the page was allocated with
+        unsafe {
+          alloc::dealloc(
+            mem.cast().as_ptr(),
+            Layout::array::<u8>(mem.len()).unwrap(),
+          )
+        };
+      },
+      // note: the mappings are unmapped when the `MmapStorage` is dropped
+      #[cfg(feature = "mmap")]
+      Storage::Mmap(_) => {},
+    }
+  }
+}
+
+/// Pages stored in memory-mapped files, one file per page
+///
+/// Each file starts with a small header identifying the format, followed by
+/// the page's memory.
+#[cfg(feature = "mmap")]
+pub(crate) struct MmapStorage {
+  dir: PathBuf,
+  /// the mappings of each page, in order
+  ///
+  /// note: this has its own lock, since the Interner's shards allocate pages
+  /// independently
+  maps: Mutex<Vec<MmapMut>>,
+  /// held to stop other processes opening the same pool
+  _lock: File,
+}
+
+#[cfg(feature = "mmap")]
+impl MmapStorage {
+  /// Identifies a file as a sinter page
+  const MAGIC: [u8; 8] = *b"SINTERPG";
+  /// The version of the page format. This must change whenever the record
+  /// layout or the hash function changes.
+  const VERSION: u32 = 2;
+  /// Written in native byte order, to detect pages from a machine with a
+  /// different endianness
+  const BYTE_ORDER: u32 = 0x0102_0304;
+  const SIZE_OF_FILE_HEADER: usize = 16;
+
+  /// Open the pages stored in `dir`, creating the directory if it doesn't
+  /// exist. Returns the memory of each existing page, in order.
+  pub(crate) fn open(dir: &Path) -> io::Result<(Self, Vec<NonNull<[u8]>>)> {
+    fs::create_dir_all(dir)?;
+    let lock = File::create(dir.join("lock"))?;
+    lock.try_lock().map_err(|e| match e {
+      TryLockError::WouldBlock => io::Error::new(
+        io::ErrorKind::WouldBlock,
+        "the pool is already open in another process",
+      ),
+      TryLockError::Error(e) => e,
+    })?;
+
+    let mut storage = MmapStorage {
+      dir: dir.to_owned(),
+      maps: Mutex::new(Vec::new()),
+      _lock: lock,
+    };
+    let maps = storage.maps.get_mut();
+    let mut pages = Vec::new();
+    loop {
+      let path = Self::page_path(&storage.dir, maps.len());
+      let file = match OpenOptions::new().read(true).write(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+        Err(e) => return Err(e),
+      };
+      // safety: the lock stops other instances of this crate from modifying
+      // the file while it's mapped
+      let mut map = unsafe { MmapMut::map_mut(&file)? };
+      if map.len() < Self::SIZE_OF_FILE_HEADER
+        || map[..8] != Self::MAGIC
+        || map[8..12] != Self::VERSION.to_ne_bytes()
+        || map[12..16] != Self::BYTE_ORDER.to_ne_bytes()
+      {
+        return Err(io::Error::new(
+          io::ErrorKind::InvalidData,
+          format!("{} is not a compatible sinter page", path.display()),
+        ));
+      }
+      pages.push(Self::page_mem(&mut map));
+      maps.push(map);
+    }
+    Ok((storage, pages))
+  }
+
+  /// Create a new page file with room for `capacity` bytes
+  fn alloc(&self, capacity: usize) -> io::Result<NonNull<[u8]>> {
+    let mut maps = self.maps.lock();
+    let path = Self::page_path(&self.dir, maps.len());
+    let file = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create_new(true)
+      .open(&path)?;
+    let map = file
+      .set_len((Self::SIZE_OF_FILE_HEADER + capacity) as u64)
+      // safety: see `open`
+      .and_then(|()| unsafe { MmapMut::map_mut(&file) });
+    let mut map = match map {
+      Ok(map) => map,
+      Err(e) => {
+        // remove the file, so that a later attempt can create it again
+        let _ = fs::remove_file(&path);
+        return Err(e);
+      },
+    };
+    map[..8].copy_from_slice(&Self::MAGIC);
+    map[8..12].copy_from_slice(&Self::VERSION.to_ne_bytes());
+    map[12..16].copy_from_slice(&Self::BYTE_ORDER.to_ne_bytes());
+    let mem = Self::page_mem(&mut map);
+    maps.push(map);
+    Ok(mem)
+  }
+
+  /// Flush all of the pages to disk
+  pub(crate) fn flush(&self) -> io::Result<()> {
+    self.maps.lock().iter().try_for_each(|map| map.flush())
+  }
+
+  fn page_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{index:08}.page"))
+  }
+
+  /// The memory of the page, after the file header
+  ///
+  /// note: the mapping doesn't move when the `MmapMut` does, and it lives
+  /// until the `MmapStorage` is dropped, which the Interner only does after
+  /// it's done with its pages
+  fn page_mem(map: &mut MmapMut) -> NonNull<[u8]> {
+    NonNull::from(&mut map[Self::SIZE_OF_FILE_HEADER..])
+  }
+}
@@ -0,0 +1,89 @@
+use {
+  crate::IStr,
+  ::core::{
+    fmt::{self, Debug, Display},
+    num::NonZeroU32,
+  },
+};
+
+/// A compact 32-bit handle to an interned string
+///
+/// Each string interned in the global pool is given a `Sym` number, counting
+/// up from 1 in the order the strings were interned. A `Sym` is half the size
+/// of an [`IStr`] and resolves back to its `IStr` in O(1), without locking.
+///
+/// ```rust
+/// # use sinter::{intern, Sym};
+/// let istr = intern("sym");
+/// let sym: Sym = istr.sym();
+/// assert_eq!(sym.as_istr(), istr);
+/// assert_eq!(::core::mem::size_of::<Option<Sym>>(), 4);
+/// ```
+#[derive(Eq, PartialEq, Copy, Clone, PartialOrd, Ord, Hash)]
+pub struct Sym(pub(super) NonZeroU32);
+
+impl Sym {
+  /// Intern a new string, or return the extant [`Sym`] if one exists
+  ///
+  /// This operation may be slow, depending on whether the string has been
+  /// previously interned.
+  #[inline]
+  pub fn new(s: &str) -> Self {
+    crate::intern(s).sym()
+  }
+
+  /// Get the [`Sym`] with the given number, if a string with that number has
+  /// been interned
+  #[inline]
+  pub fn from_u32(n: u32) -> Option<Self> {
+    let n = NonZeroU32::new(n)?;
+    crate::interner::THE_INTERNER.resolve_sym(n).map(|_| Sym(n))
+  }
+
+  /// The number of this [`Sym`]
+  #[inline]
+  pub fn to_u32(self) -> u32 {
+    self.0.get()
+  }
+
+  /// Locklessly resolve the [`IStr`] this [`Sym`] refers to
+  #[inline]
+  pub fn as_istr(self) -> IStr {
+    // note: a `Sym` can only be created for a string that has been interned
+    crate::interner::THE_INTERNER.resolve_sym(self.0).unwrap()
+  }
+
+  /// Locklessly resolve the `&str` this [`Sym`] refers to
+  #[inline]
+  pub fn as_str(self) -> &'static str {
+    self.as_istr().as_str()
+  }
+}
+
+impl From<Sym> for IStr {
+  #[inline]
+  fn from(sym: Sym) -> IStr {
+    sym.as_istr()
+  }
+}
+
+impl From<IStr> for Sym {
+  #[inline]
+  fn from(istr: IStr) -> Sym {
+    istr.sym()
+  }
+}
+
+impl Display for Sym {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+impl Debug for Sym {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_fmt(format_args!("Sym({}, \"{}\")", self.0, self.as_str()))
+  }
+}
@@ -0,0 +1,127 @@
+//! The synchronisation primitives used by the lock-free tables
+//!
+//! These are swapped for [loom](https://crates.io/crates/loom)'s models when
+//! the crate is built with `RUSTFLAGS="--cfg loom"`, so that the model tests
+//! can explore every interleaving of the readers & the writer:
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --release --lib loom
+//! ```
+
+#[cfg(loom)]
+pub(crate) use ::loom::{sync::atomic, thread};
+#[cfg(not(loom))]
+pub(crate) use {::core::sync::atomic, ::std::thread};
+
+/// Declares a `const fn`, which is a plain `fn` under loom, since loom's
+/// atomics can't be created in a const context
+macro_rules! const_fn {
+  ($(#[$attr:meta])* $vis:vis const fn $($rest:tt)*) => {
+    #[cfg(not(loom))]
+    $(#[$attr])* $vis const fn $($rest)*
+    #[cfg(loom)]
+    $(#[$attr])* $vis fn $($rest)*
+  };
+}
+pub(crate) use const_fn;
+
+/// A mutex that isn't tied to a guard's scope
+#[cfg(not(loom))]
+pub(crate) struct RawMutex(::parking_lot::RawMutex);
+
+#[cfg(not(loom))]
+impl RawMutex {
+  pub(crate) const fn new() -> Self {
+    RawMutex(
+      <::parking_lot::RawMutex as ::parking_lot::lock_api::RawMutex>::INIT,
+    )
+  }
+
+  #[inline]
+  pub(crate) fn lock(&self) {
+    ::parking_lot::lock_api::RawMutex::lock(&self.0)
+  }
+
+  // note: only used to count contended locks
+  #[cfg_attr(not(feature = "counters"), allow(dead_code))]
+  #[inline]
+  pub(crate) fn try_lock(&self) -> bool {
+    ::parking_lot::lock_api::RawMutex::try_lock(&self.0)
+  }
+
+  /// # Safety
+  ///
+  /// - must only be called by the holder of the lock
+  #[inline]
+  pub(crate) unsafe fn unlock(&self) {
+    // safety: the caller holds the lock
+    unsafe { ::parking_lot::lock_api::RawMutex::unlock(&self.0) }
+  }
+}
+
+/// A mutex that isn't tied to a guard's scope
+///
+/// note: a spin lock, since loom's `Mutex` can only be unlocked by dropping
+/// its guard
+#[cfg(loom)]
+pub(crate) struct RawMutex(atomic::AtomicBool);
+
+#[cfg(loom)]
+impl RawMutex {
+  pub(crate) fn new() -> Self {
+    RawMutex(atomic::AtomicBool::new(false))
+  }
+
+  pub(crate) fn lock(&self) {
+    while !self.try_lock() {
+      thread::yield_now();
+    }
+  }
+
+  pub(crate) fn try_lock(&self) -> bool {
+    self
+      .0
+      .compare_exchange(
+        false,
+        true,
+        atomic::Ordering::Acquire,
+        atomic::Ordering::Relaxed,
+      )
+      .is_ok()
+  }
+
+  /// # Safety
+  ///
+  /// - must only be called by the holder of the lock
+  pub(crate) unsafe fn unlock(&self) {
+    self.0.store(false, atomic::Ordering::Release);
+  }
+}
+
+/// An `UnsafeCell` whose accesses are checked by loom
+///
+/// Outside of loom this is a plain [`UnsafeCell`][core::cell::UnsafeCell]
+/// with loom's closure-based API.
+#[cfg(not(loom))]
+pub(crate) struct UnsafeCell<T>(::core::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+  #[inline]
+  pub(crate) const fn new(value: T) -> Self {
+    UnsafeCell(::core::cell::UnsafeCell::new(value))
+  }
+
+  #[inline]
+  pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+    f(self.0.get())
+  }
+
+  #[inline]
+  pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+    f(self.0.get())
+  }
+}
+
+#[cfg(loom)]
+pub(crate) use ::loom::cell::UnsafeCell;
@@ -0,0 +1,232 @@
+//! String-table compressed encoding of [`IStr`]-heavy data
+//!
+//! A [`Writer`] stores each distinct [`IStr`] written to it just once, in a
+//! string table at the start of the output, and encodes each occurrence as a
+//! varint index into that table. A [`Reader`] interns the whole string table
+//! when it's created, so reading an [`IStr`] back is just a table lookup.
+//!
+//! ```rust
+//! # use sinter::{intern, table::{Reader, Writer}};
+//! let mut writer = Writer::new();
+//! for _ in 0..1000 {
+//!   writer.write_istr(intern("repeated"));
+//!   writer.write_u64(42);
+//! }
+//! let mut bytes = Vec::new();
+//! writer.finish(&mut bytes).unwrap();
+//!
+//! let mut reader = Reader::new(bytes.as_slice()).unwrap();
+//! for _ in 0..1000 {
+//!   assert!(reader.read_istr().unwrap() == intern("repeated"));
+//!   assert_eq!(reader.read_u64().unwrap(), 42);
+//! }
+//! ```
+//!
+//! The format is a varint count of strings, then each string as a varint
+//! length followed by its bytes, then everything written to the [`Writer`].
+//! Varints are unsigned LEB128.
+
+use {
+  crate::{
+    interner::{InternError, THE_INTERNER},
+    IStr,
+  },
+  ::hashbrown::HashTable,
+  ::std::io::{self, Read, Write},
+};
+
+/// Encodes data containing [`IStr`]s, writing each distinct [`IStr`] once
+///
+/// The data is buffered until [`Writer::finish`] is called, since the string
+/// table must come first. Other data can be written with [`Writer::write_u64`]
+/// or the [`Write`] impl.
+#[derive(Default)]
+pub struct Writer {
+  /// indices into `strings`, keyed by the `IStr`'s wyhash
+  indices: HashTable<u32>,
+  /// the string table, in order of first occurrence
+  strings: Vec<IStr>,
+  /// everything else written to the writer
+  body: Vec<u8>,
+}
+
+impl Writer {
+  /// Create a new empty `Writer`
+  #[inline]
+  pub fn new() -> Self {
+    Writer::default()
+  }
+
+  /// Write an [`IStr`] as its index in the string table
+  pub fn write_istr(&mut self, istr: IStr) {
+    let strings = &mut self.strings;
+    let index = *self
+      .indices
+      .entry(
+        istr.wyhash(),
+        // note: it's sufficient to compare the `IStr`s, which is just a
+        // pointer comparison
+        |&i| strings[i as usize] == istr,
+        |&i| strings[i as usize].wyhash(),
+      )
+      .or_insert_with(|| {
+        let index = u32::try_from(strings.len())
+          .expect("the string table can't have more than u32::MAX strings");
+        strings.push(istr);
+        index
+      })
+      .get();
+    write_varint(&mut self.body, index as u64);
+  }
+
+  /// Write a `u64` as a varint
+  #[inline]
+  pub fn write_u64(&mut self, n: u64) {
+    write_varint(&mut self.body, n);
+  }
+
+  /// The number of distinct [`IStr`]s written so far
+  #[inline]
+  pub fn table_len(&self) -> usize {
+    self.strings.len()
+  }
+
+  /// Write the string table followed by everything written to this `Writer`
+  pub fn finish<W: Write>(self, w: &mut W) -> io::Result<()> {
+    let mut table = Vec::new();
+    write_varint(&mut table, self.strings.len() as u64);
+    for istr in &self.strings {
+      write_varint(&mut table, istr.len() as u64);
+      table.extend_from_slice(istr.as_bytes());
+    }
+    w.write_all(&table)?;
+    w.write_all(&self.body)
+  }
+}
+
+impl Write for Writer {
+  /// Write raw bytes
+  #[inline]
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.body.write(buf)
+  }
+
+  #[inline]
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Decodes data written by a [`Writer`]
+pub struct Reader<R> {
+  /// the interned string table
+  strings: Vec<IStr>,
+  inner: R,
+}
+
+impl<R: Read> Reader<R> {
+  /// Read & intern the string table from `inner`
+  ///
+  /// If the memory needed to store the strings can't be allocated, or would
+  /// take the global pool over its byte budget, an error of kind
+  /// [`io::ErrorKind::OutOfMemory`] is returned, wrapping the
+  /// [`InternError`], and the strings interned before
+  /// the failure are kept. A string too long to intern is
+  /// [`io::ErrorKind::InvalidData`].
+  pub fn new(mut inner: R) -> io::Result<Self> {
+    let len = read_varint(&mut inner)?;
+    let mut table = Vec::new();
+    for _ in 0..len {
+      let s_len = read_varint(&mut inner)?;
+      let mut buf = Vec::new();
+      (&mut inner).take(s_len).read_to_end(&mut buf)?;
+      if buf.len() as u64 != s_len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+      }
+      let s = String::from_utf8(buf)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+      table.push(s);
+    }
+    // note: the whole table is interned at once, since most of the strings
+    // are likely to be new
+    let strings = THE_INTERNER.try_intern_many_istrs(table).map_err(|e| {
+      let kind = match e {
+        InternError::TooLong => io::ErrorKind::InvalidData,
+        _ => io::ErrorKind::OutOfMemory,
+      };
+      io::Error::new(kind, e)
+    })?;
+    Ok(Reader { strings, inner })
+  }
+
+  /// Read an [`IStr`] written by [`Writer::write_istr`]
+  #[inline]
+  pub fn read_istr(&mut self) -> io::Result<IStr> {
+    let index = read_varint(&mut self.inner)?;
+    match self.strings.get(index as usize) {
+      Some(&istr) => Ok(istr),
+      None => Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "string table index out of bounds",
+      )),
+    }
+  }
+
+  /// Read a `u64` written by [`Writer::write_u64`]
+  #[inline]
+  pub fn read_u64(&mut self) -> io::Result<u64> {
+    read_varint(&mut self.inner)
+  }
+
+  /// The interned string table
+  #[inline]
+  pub fn table(&self) -> &[IStr] {
+    &self.strings
+  }
+
+  /// Unwrap this `Reader`, returning the underlying reader
+  #[inline]
+  pub fn into_inner(self) -> R {
+    self.inner
+  }
+}
+
+impl<R: Read> Read for Reader<R> {
+  /// Read raw bytes
+  #[inline]
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    self.inner.read(buf)
+  }
+}
+
+/// Write `n` as an unsigned LEB128 varint
+#[inline]
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+  while n >= 0x80 {
+    out.push((n as u8) | 0x80);
+    n >>= 7;
+  }
+  out.push(n as u8);
+}
+
+/// Read an unsigned LEB128 varint
+#[inline]
+fn read_varint<R: Read>(r: &mut R) -> io::Result<u64> {
+  let mut n = 0;
+  for shift in (0..u64::BITS).step_by(7) {
+    let mut byte = [0];
+    r.read_exact(&mut byte)?;
+    let bits = (byte[0] & 0x7f) as u64;
+    if shift > 0 && bits >> (u64::BITS - shift) != 0 {
+      break;
+    }
+    n |= bits << shift;
+    if byte[0] & 0x80 == 0 {
+      return Ok(n);
+    }
+  }
+  Err(io::Error::new(
+    io::ErrorKind::InvalidData,
+    "varint overflows a u64",
+  ))
+}
@@ -1,9 +1,22 @@
+// these tests deliberately exercise the impls with owned values & references
+#![allow(clippy::deref_addrof, clippy::unnecessary_to_owned)]
+
 use {
   super::*,
   ::core::{ffi::CStr, iter},
   ::std::ffi::CString,
 };
 
+/// The number of iterations for a test, which is scaled down under Miri since
+/// it's much slower
+const fn iterations(n: usize) -> usize {
+  if cfg!(miri) {
+    n / 10
+  } else {
+    n
+  }
+}
+
 #[test]
 fn eq() {
   let hello_world = intern(&"hello, world".to_owned());
@@ -112,6 +125,7 @@ fn partial_eq() {
 }
 
 #[test]
+#[allow(clippy::manual_repeat_n)]
 fn long_strings() {
   let hello = intern("hello");
   let e = intern(&String::from_iter(iter::repeat('E').take(4000)));
@@ -144,7 +158,7 @@ fn concurrency() {
   let mut c = HashMap::new();
   let mut d = HashMap::new();
 
-  const COUNT: usize = 1_000;
+  const COUNT: usize = iterations(1_000);
 
   thread::scope(|s| {
     s.spawn(|| {
@@ -246,3 +260,1490 @@ fn _collect() {
 
   assert_eq!(istrs, set);
 }
+
+#[test]
+fn interner_collect() {
+  use ::std::collections::HashSet;
+
+  const COUNT: usize = 100;
+
+  let interner = Interner::new();
+
+  let mut set = HashSet::new();
+  for i in 0..COUNT {
+    set.insert(interner.intern(&format!("{i}")));
+  }
+
+  let mut pool_strs = interner.collect_interned_strings::<Vec<_>>();
+  pool_strs.sort();
+
+  let mut set = set.into_iter().collect::<Vec<_>>();
+  set.sort();
+
+  assert_eq!(pool_strs, set);
+}
+
+#[test]
+fn interner_isolation() {
+  let a = Interner::new();
+  let b = Interner::new();
+
+  let a_hello = a.intern("hello");
+  let b_hello = b.intern("hello");
+  let a_world = a.intern("world");
+
+  assert_eq!(a_hello, a.intern("hello"));
+  assert_eq!(a_hello, "hello");
+  assert_eq!(a_hello.as_str(), b_hello.as_str());
+  assert_ne!(
+    a_hello, b_hello,
+    "strings from different pools are distinct"
+  );
+  assert_ne!(a_hello, a_world);
+
+  assert!(b.get_interned("world").is_none());
+  assert!(get_interned("interner_isolation").is_none());
+  a.intern("interner_isolation");
+  assert!(get_interned("interner_isolation").is_none());
+}
+
+#[test]
+fn interner_drop() {
+  for _ in 0..10 {
+    let interner = Interner::new();
+    let long = String::from_iter(iter::repeat_n('L', 3000));
+    for i in 0..iterations(1_000) {
+      interner.intern(&format!("{i}"));
+    }
+    assert_eq!(interner.intern(&long).as_str(), long);
+  }
+}
+
+#[test]
+fn interner_concurrency() {
+  use ::std::thread;
+
+  const COUNT: usize = iterations(1_000);
+
+  let interner = Interner::new();
+
+  let results = thread::scope(|s| {
+    let handles = (0..4)
+      .map(|_| {
+        s.spawn(|| {
+          (0..COUNT)
+            .map(|i| interner.intern(&format!("{i}")))
+            .collect::<Vec<_>>()
+        })
+      })
+      .collect::<Vec<_>>();
+    handles
+      .into_iter()
+      .map(|h| h.join().unwrap())
+      .collect::<Vec<_>>()
+  });
+
+  for result in &results[1..] {
+    assert_eq!(
+      &results[0], result,
+      "Interning the same strings from different threads should be Eq"
+    );
+  }
+}
+
+#[test]
+fn shards() {
+  use ::std::{collections::HashSet, thread};
+
+  const COUNT: usize = iterations(1_000);
+
+  let interner = Interner::new();
+  // each thread interns its own strings, and some shared ones
+  let results = thread::scope(|s| {
+    let handles = (0..4)
+      .map(|t| {
+        let interner = &interner;
+        s.spawn(move || {
+          (0..COUNT)
+            .flat_map(|i| {
+              [
+                interner.intern(&format!("shards_{t}_{i}")),
+                interner.intern(&format!("shards_{i}")),
+              ]
+            })
+            .collect::<Vec<_>>()
+        })
+      })
+      .collect::<Vec<_>>();
+    handles
+      .into_iter()
+      .map(|h| h.join().unwrap())
+      .collect::<Vec<_>>()
+  });
+
+  // the strings are spread over the shards
+  let stats = interner.stats();
+  assert_eq!(stats.strings, 5 * COUNT);
+  let shards: HashSet<usize> = stats.pages.iter().map(|p| p.shard).collect();
+  assert!(shards.len() > 1);
+
+  // every string has a distinct id, with none skipped
+  let mut ids: Vec<u32> = results.iter().flatten().map(|p| p.id()).collect();
+  ids.sort_unstable();
+  ids.dedup();
+  assert_eq!(ids, (1..=5 * COUNT as u32).collect::<Vec<_>>());
+  for (t, result) in results.iter().enumerate() {
+    for (i, pair) in result.chunks(2).enumerate() {
+      assert_eq!(pair[0], format!("shards_{t}_{i}").as_str());
+      assert_eq!(pair[1], results[0][2 * i + 1]);
+      assert_eq!(interner.resolve(pair[0].id()), Some(pair[0]));
+    }
+  }
+
+  // every shard is collected
+  let collected: HashSet<PoolStr> = interner.collect_interned_strings();
+  assert_eq!(collected.len(), 5 * COUNT);
+  assert!(results.iter().flatten().all(|p| collected.contains(p)));
+}
+
+#[test]
+fn intern_many() {
+  use ::std::thread;
+
+  let interner = Interner::new();
+  let a = interner.intern("many_a");
+  let many = interner.intern_many(["many_b", "many_a", "many_c", "many_b"]);
+  assert_eq!(many, ["many_b", "many_a", "many_c", "many_b"]);
+  assert_eq!(many[1], a);
+  assert_eq!(many[0], many[3]);
+  // new strings get their ids in the order they were given
+  assert_eq!(
+    many.iter().map(|p| p.id()).collect::<Vec<_>>(),
+    [2, 1, 3, 2]
+  );
+  assert_eq!(interner.stats().strings, 3);
+  assert_eq!(interner.get_interned("many_c"), Some(many[2]));
+  assert!(interner.intern_many(iter::empty::<&str>()).is_empty());
+
+  let mut out = vec![a];
+  interner.intern_many_into(vec![String::from("many_d")], &mut out);
+  assert_eq!(out, ["many_a", "many_d"]);
+
+  // batches racing each other & single interns agree
+  const COUNT: usize = iterations(1000);
+  let strings: Vec<String> = (0..COUNT).map(|i| format!("many_{i}")).collect();
+  let results = thread::scope(|scope| {
+    let handles: Vec<_> = (0..8)
+      .map(|t| {
+        let strings = &strings;
+        let interner = &interner;
+        scope.spawn(move || match t % 2 {
+          0 => interner.intern_many(strings.iter().skip(t * 10)),
+          _ => strings
+            .iter()
+            .skip(t * 10)
+            .map(|s| interner.intern(s))
+            .collect(),
+        })
+      })
+      .collect();
+    handles
+      .into_iter()
+      .map(|h| h.join().unwrap())
+      .collect::<Vec<_>>()
+  });
+  for (t, result) in results.iter().enumerate() {
+    assert_eq!(result[..], results[0][t * 10..]);
+  }
+
+  // the global pool
+  let istrs =
+    crate::intern_many(&["intern_many_global", "intern_many_global"]);
+  assert_eq!(istrs[0], intern("intern_many_global"));
+  assert_eq!(istrs[0], istrs[1]);
+}
+
+// note: measures the writer's cpu time with `/proc/thread-self/schedstat`,
+// which depends on how busy the machine is, so it's only run on request:
+// `cargo test --release -- --ignored writer_parks`
+#[cfg(target_os = "linux")]
+#[test]
+#[ignore = "timing-sensitive"]
+fn writer_parks() {
+  use {
+    crate::double_table::DoubleTable,
+    ::core::sync::atomic::{AtomicBool, Ordering},
+    ::std::{
+      sync::Barrier,
+      thread,
+      time::{Duration, Instant},
+    },
+  };
+
+  /// the cpu time used by the current thread
+  fn cpu_time() -> Duration {
+    let schedstat =
+      ::std::fs::read_to_string("/proc/thread-self/schedstat").unwrap();
+    let nanos = schedstat.split_whitespace().next().unwrap();
+    Duration::from_nanos(nanos.parse().unwrap())
+  }
+
+  // oversubscribe the machine with readers, which block for a while in the
+  // middle of their reads
+  let readers = 4 * thread::available_parallelism().map_or(1, |n| n.get());
+  let table = DoubleTable::<u64>::new();
+  let done = AtomicBool::new(false);
+  let started = Barrier::new(readers + 1);
+  let (wall, cpu) = thread::scope(|s| {
+    for _ in 0..readers {
+      s.spawn(|| {
+        started.wait();
+        while !done.load(Ordering::Relaxed) {
+          table.read(|_| thread::sleep(Duration::from_millis(2)));
+        }
+      });
+    }
+    started.wait();
+    let (wall, cpu) = (Instant::now(), cpu_time());
+    for i in 0..50 {
+      // safety: this is the only writer
+      unsafe {
+        table.writer_table();
+        table.publish(i, i, |&v| v);
+      }
+    }
+    let wall_cpu = (wall.elapsed(), cpu_time() - cpu);
+    done.store(true, Ordering::Relaxed);
+    wall_cpu
+  });
+  assert_eq!(table.read(|t| t.unwrap().len()), 50);
+
+  // note: a writer that spins & yields uses about half of the time it waits,
+  // since the readers are blocked, leaving the cpu free. A parked writer
+  // should use hardly any.
+  assert!(cpu < wall / 10, "used {cpu:?} of {wall:?}");
+}
+
+#[test]
+fn writer_waits_without_epochs_lock() {
+  use {
+    crate::double_table::DoubleTable,
+    ::std::{
+      sync::{mpsc, Barrier},
+      thread,
+      time::Duration,
+    },
+  };
+
+  let table = DoubleTable::<u64>::new();
+  let other = DoubleTable::<u64>::new();
+  let reading = Barrier::new(2);
+  let (release, released) = mpsc::channel::<()>();
+  thread::scope(|s| {
+    // a reader in the middle of a long read
+    let (table, reading) = (&table, &reading);
+    s.spawn(move || {
+      table.read(|_| {
+        reading.wait();
+        released.recv().unwrap();
+      })
+    });
+    reading.wait();
+    // the writer of another table, waiting for the read to finish
+    s.spawn(|| {
+      // safety: this is the only writer
+      unsafe {
+        other.writer_table();
+        other.publish(1, 1, |&v| v);
+      }
+    });
+    thread::sleep(Duration::from_millis(20));
+
+    // a new thread can register its epoch, and the epochs can be counted,
+    // while the writer waits
+    let (done, finished) = mpsc::channel();
+    s.spawn(move || {
+      assert!(get_interned("writer_waits_without_epochs_lock").is_none());
+      assert!(Interner::new().stats().thread_epochs > 0);
+      done.send(()).unwrap();
+    });
+    let result = finished.recv_timeout(Duration::from_secs(10));
+    release.send(()).unwrap();
+    result.expect("a new reader waited for the writer");
+  });
+  assert_eq!(other.read(|t| t.unwrap().len()), 1);
+}
+
+#[test]
+fn sym() {
+  let a = intern("sym_a");
+  let b = intern("sym_b");
+
+  assert_eq!(a.sym().as_istr(), a);
+  assert_eq!(b.sym().as_istr(), b);
+  assert_eq!(Sym::new("sym_a"), a.sym());
+  assert_eq!(Sym::from_u32(a.sym().to_u32()), Some(a.sym()));
+  assert_eq!(Sym::from_u32(0), None);
+  assert_eq!(Sym::from_u32(u32::MAX), None);
+  assert!(
+    a.sym() < b.sym(),
+    "Syms should be given out in insertion order"
+  );
+
+  assert_eq!(::core::mem::size_of::<Option<Sym>>(), 4);
+}
+
+#[test]
+fn sym_index() {
+  use ::core::num::NonZeroU32;
+
+  const COUNT: u32 = iterations(5_000) as u32;
+
+  let interner = Interner::new();
+  for i in 0..COUNT {
+    interner.intern(&format!("{i}"));
+  }
+  for i in 0..COUNT {
+    let sym = NonZeroU32::new(i + 1).unwrap();
+    let istr = interner.resolve_sym(sym).unwrap();
+    assert_eq!(istr.as_str(), format!("{i}"));
+    assert_eq!(istr.sym().0, sym);
+  }
+  assert!(interner
+    .resolve_sym(NonZeroU32::new(COUNT + 1).unwrap())
+    .is_none());
+}
+
+#[test]
+fn thin() {
+  use ::core::mem::size_of;
+
+  assert_eq!(size_of::<IStr>(), size_of::<usize>());
+  assert_eq!(size_of::<Option<IStr>>(), size_of::<usize>());
+
+  let empty = intern("");
+  assert_eq!(empty.as_str(), "");
+  assert_eq!(empty.as_c_str(), c"");
+  assert_eq!(empty, intern(""));
+
+  let mut istrs = [intern("thin_b"), intern("thin_c"), intern("thin_a")];
+  istrs.sort();
+  assert_eq!(istrs, ["thin_a", "thin_b", "thin_c"]);
+}
+
+#[test]
+fn const_wyhash() {
+  use crate::{const_wyhash::wyhash, interner::WYHASH_SEED};
+
+  let bytes = (0..=255u8).cycle().take(300).collect::<Vec<_>>();
+  for len in 0..bytes.len() {
+    for seed in [WYHASH_SEED, 1, u64::MAX] {
+      assert_eq!(
+        wyhash(&bytes[..len], seed),
+        ::wyhash::wyhash(&bytes[..len], seed),
+        "const wyhash should match the wyhash crate"
+      );
+    }
+  }
+}
+
+#[test]
+fn istr_macro() {
+  let mut istrs = Vec::new();
+  for _ in 0..3 {
+    istrs.push(istr!("istr_macro"));
+  }
+  assert_eq!(istrs, [intern("istr_macro"); 3]);
+  assert_eq!(istr!(""), intern(""));
+}
+
+static_table! {
+  static TEST_TABLE;
+  const KW_FN: IStr = "static_table_fn";
+  const KW_LET: IStr = "static_table_let";
+  const KW_LONG: IStr = "static_table_keyword_longer_than_thirty_two_bytes";
+}
+
+static_table! {
+  static LAYOUT_TABLE;
+  const KW_EMPTY: IStr = "";
+}
+
+#[test]
+fn static_table() {
+  assert_eq!(TEST_TABLE.len(), 3);
+  assert_eq!(KW_FN.as_str(), "static_table_fn");
+  assert_eq!(KW_LET.as_c_str(), c"static_table_let");
+  assert_eq!(KW_EMPTY.as_str(), "");
+  assert_eq!(
+    KW_LONG.wyhash(),
+    ::wyhash::wyhash(KW_LONG.as_bytes(), crate::interner::WYHASH_SEED)
+  );
+  // the strings have no syms until the table is registered
+  assert!(::std::panic::catch_unwind(|| KW_LET.sym()).is_err());
+
+  TEST_TABLE.register().unwrap();
+  assert_eq!(intern("static_table_fn"), KW_FN);
+  assert_eq!(intern("static_table_let"), KW_LET);
+  assert_eq!(get_interned(&KW_LONG), Some(KW_LONG));
+  assert_eq!(KW_LET.sym().to_u32(), KW_FN.sym().to_u32() + 1);
+  assert_eq!(KW_LET.sym().as_istr(), KW_LET);
+  assert_eq!(Sym::from_u32(KW_FN.sym().to_u32()), Some(KW_FN.sym()));
+  assert_eq!(TEST_TABLE.register(), Err(RegisterError::AlreadyRegistered));
+
+  // more tables can be registered, unless their strings are already interned
+  let other = intern("static_table_other");
+  assert_eq!(
+    OVERLAPPING_TABLE.register(),
+    Err(RegisterError::AlreadyInterned("static_table_other"))
+  );
+  assert!(get_interned(&KW_STRUCT).is_none());
+  assert_ne!(other, KW_OTHER);
+  assert!(::std::panic::catch_unwind(|| KW_MATCH.sym()).is_err());
+  SECOND_TABLE.register().unwrap();
+  assert_eq!(intern("static_table_match"), KW_MATCH);
+  assert_eq!(KW_MATCH.sym().as_istr(), KW_MATCH);
+}
+
+static_table! {
+  static OVERLAPPING_TABLE;
+  const KW_STRUCT: IStr = "static_table_struct";
+  const KW_OTHER: IStr = "static_table_other";
+}
+
+static_table! {
+  static SECOND_TABLE;
+  const KW_MATCH: IStr = "static_table_match";
+}
+
+static_table! {
+  static DUPLICATE_TABLE;
+  const DUP_A: IStr = "static_table_dup";
+  const DUP_B: IStr = "static_table_dup";
+}
+
+#[test]
+fn static_table_duplicate() {
+  assert_eq!(DUP_A, DUP_A);
+  assert_ne!(DUP_A, DUP_B);
+  assert_eq!(
+    DUPLICATE_TABLE.register(),
+    Err(RegisterError::Duplicate("static_table_dup"))
+  );
+  assert!(get_interned("static_table_dup").is_none());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_json() {
+  use ::std::collections::HashMap;
+
+  let istr = intern("serde_json");
+  let json = ::serde_json::to_string(&istr).unwrap();
+  assert_eq!(json, r#""serde_json""#);
+  assert_eq!(::serde_json::from_str::<IStr>(&json).unwrap(), istr);
+
+  // owned input
+  let value = ::serde_json::Value::String("serde_json_owned".to_owned());
+  let from_value: IStr = ::serde_json::from_value(value).unwrap();
+  assert_eq!(from_value, intern("serde_json_owned"));
+
+  // escaped strings can't be borrowed from the input
+  let escaped: IStr = ::serde_json::from_str(r#""serde\njson""#).unwrap();
+  assert_eq!(escaped, intern("serde\njson"));
+
+  let map = HashMap::from([(intern("key"), vec![istr, intern("value")])]);
+  let json = ::serde_json::to_string(&map).unwrap();
+  let map2: HashMap<IStr, Vec<IStr>> = ::serde_json::from_str(&json).unwrap();
+  assert_eq!(map, map2);
+
+  let sym = Sym::new("serde_json_sym");
+  let json = ::serde_json::to_string(&sym).unwrap();
+  assert_eq!(json, r#""serde_json_sym""#);
+  assert_eq!(::serde_json::from_str::<Sym>(&json).unwrap(), sym);
+
+  assert!(::serde_json::from_str::<IStr>("1234").is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_bincode() {
+  let istrs =
+    vec![intern("serde_bincode"), intern(""), intern("serde_bincode")];
+  let bytes = ::bincode::serialize(&istrs).unwrap();
+  let istrs2: Vec<IStr> = ::bincode::deserialize(&bytes).unwrap();
+  assert_eq!(istrs, istrs2);
+
+  let interner = Interner::new();
+  let pool_str = interner.intern("serde_bincode_pool_str");
+  let bytes = ::bincode::serialize(&pool_str).unwrap();
+  let istr: IStr = ::bincode::deserialize(&bytes).unwrap();
+  assert_eq!(istr, "serde_bincode_pool_str");
+
+  let invalid = ::bincode::serialize(&[0xffu8, 0xfe][..]).unwrap();
+  assert!(::bincode::deserialize::<IStr>(&invalid).is_err());
+}
+
+#[test]
+fn table() {
+  use crate::table::{Reader, Writer};
+  use ::std::io::{Read, Write};
+
+  let words = ["table_a", "table_b", "", "table_a", "table_c", "table_b"];
+
+  let mut writer = Writer::new();
+  for (i, word) in words.iter().enumerate() {
+    writer.write_istr(intern(word));
+    writer.write_u64(i as u64 * 1_000_000);
+  }
+  writer.write_u64(u64::MAX);
+  writer.write_all(b"raw").unwrap();
+  assert_eq!(writer.table_len(), 4);
+
+  let mut bytes = Vec::new();
+  writer.finish(&mut bytes).unwrap();
+
+  let mut reader = Reader::new(bytes.as_slice()).unwrap();
+  assert_eq!(reader.table(), ["table_a", "table_b", "", "table_c"]);
+  for (i, word) in words.iter().enumerate() {
+    assert_eq!(reader.read_istr().unwrap(), intern(word));
+    assert_eq!(reader.read_u64().unwrap(), i as u64 * 1_000_000);
+  }
+  assert_eq!(reader.read_u64().unwrap(), u64::MAX);
+  let mut raw = Vec::new();
+  reader.read_to_end(&mut raw).unwrap();
+  assert_eq!(raw, b"raw");
+  assert!(reader.read_istr().is_err());
+}
+
+#[test]
+fn table_invalid() {
+  use crate::table::Reader;
+  use ::std::io::ErrorKind;
+
+  // truncated string
+  let err = Reader::new(&[1, 5, b'a'][..]).err().unwrap();
+  assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+  // invalid utf-8
+  let err = Reader::new(&[1, 1, 0xff][..]).err().unwrap();
+  assert_eq!(err.kind(), ErrorKind::InvalidData);
+  // index out of bounds
+  let mut reader = Reader::new(&[1, 1, b'a', 1][..]).unwrap();
+  assert_eq!(
+    reader.read_istr().unwrap_err().kind(),
+    ErrorKind::InvalidData
+  );
+  // varint overflow
+  let mut reader = Reader::new(
+    &[
+      0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+    ][..],
+  )
+  .unwrap();
+  assert_eq!(
+    reader.read_u64().unwrap_err().kind(),
+    ErrorKind::InvalidData
+  );
+}
+
+#[test]
+fn snapshot() {
+  use {crate::snapshot, ::core::num::NonZeroU32};
+
+  let words = ["snapshot_a", "", "snapshot_b", "snapshot_c"];
+  let interner = Interner::new();
+  for word in words {
+    interner.intern(word);
+  }
+  let mut bytes = Vec::new();
+  interner.save_snapshot(&mut bytes).unwrap();
+
+  // loading into an empty pool reproduces the same syms
+  let fresh = Interner::new();
+  fresh.load_snapshot(bytes.as_slice()).unwrap();
+  for (i, word) in words.iter().enumerate() {
+    let sym = NonZeroU32::new(i as u32 + 1).unwrap();
+    let istr = fresh.resolve_sym(sym).unwrap();
+    assert_eq!(istr, *word);
+    assert_eq!(istr.sym().to_u32(), i as u32 + 1);
+    assert_eq!(istr.wyhash(), interner.intern(word).wyhash());
+    assert!(fresh.get_interned(word).unwrap() == PoolStr::from(istr));
+  }
+  assert!(fresh.resolve_sym(NonZeroU32::new(5).unwrap()).is_none());
+
+  // loading again is a no-op
+  fresh.load_snapshot(bytes.as_slice()).unwrap();
+  assert_eq!(
+    fresh.collect_interned_strings::<Vec<_>>().len(),
+    words.len()
+  );
+
+  // a sym without a string, such as one whose string is still being written,
+  // doesn't renumber the strings after it
+  let gap = Interner::new();
+  gap.intern("snapshot_a");
+  gap.skip_syms_through(2);
+  gap.intern("snapshot_c");
+  let mut bytes = Vec::new();
+  gap.save_snapshot(&mut bytes).unwrap();
+  let fresh = Interner::new();
+  fresh.load_snapshot(bytes.as_slice()).unwrap();
+  assert_eq!(fresh.get_interned("snapshot_a").unwrap().id(), 1);
+  assert!(fresh.resolve(2).is_none());
+  assert_eq!(fresh.get_interned("snapshot_c").unwrap().id(), 3);
+
+  // the global pool
+  intern("snapshot_global");
+  let mut global = Vec::new();
+  snapshot::save(&mut global).unwrap();
+  snapshot::load(global.as_slice()).unwrap();
+  let from_global = Interner::new();
+  from_global.load_snapshot(global.as_slice()).unwrap();
+  assert!(from_global.get_interned("snapshot_global").is_some());
+}
+
+#[test]
+fn snapshot_invalid() {
+  use crate::snapshot::Error;
+
+  let interner = Interner::new();
+  interner.intern("snapshot_invalid");
+  let mut bytes = Vec::new();
+  interner.save_snapshot(&mut bytes).unwrap();
+
+  let fresh = Interner::new();
+  let mut bad_magic = bytes.clone();
+  bad_magic[0] = b'X';
+  assert!(matches!(
+    fresh.load_snapshot(bad_magic.as_slice()),
+    Err(Error::BadMagic)
+  ));
+  let mut bad_version = bytes.clone();
+  bad_version[8] = 99;
+  assert!(matches!(
+    fresh.load_snapshot(bad_version.as_slice()),
+    Err(Error::UnsupportedVersion(99))
+  ));
+  // snapshots from before byte strings were interned are rejected too
+  bad_version[8] = 1;
+  assert!(matches!(
+    fresh.load_snapshot(bad_version.as_slice()),
+    Err(Error::UnsupportedVersion(1))
+  ));
+  // note: the checksum is fixed up, so that the records are checked
+  let fix_checksum = |bytes: &mut Vec<u8>| {
+    let len = bytes.len();
+    let (body, checksum) = bytes.split_at_mut(len - 8);
+    let fnv = body.iter().fold(0xcbf2_9ce4_8422_2325_u64, |h, &b| {
+      (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    checksum.copy_from_slice(&fnv.to_le_bytes());
+  };
+  let mut out_of_order = Vec::new();
+  let two = Interner::new();
+  two.intern("snapshot_invalid_a");
+  two.intern("snapshot_invalid_b");
+  two.save_snapshot(&mut out_of_order).unwrap();
+  let second = 20 + 16 + "snapshot_invalid_a".len();
+  out_of_order[second] = 1;
+  fix_checksum(&mut out_of_order);
+  assert!(matches!(
+    fresh.load_snapshot(out_of_order.as_slice()),
+    Err(Error::SymOutOfOrder)
+  ));
+  // a static table's sym, or a gap larger than a pool can have, is rejected
+  // before any syms are reserved
+  for sym in [0x8000_0000_u32, 0x7fff_ffff, 1_000] {
+    let mut out_of_range = bytes.clone();
+    out_of_range[20..24].copy_from_slice(&sym.to_le_bytes());
+    fix_checksum(&mut out_of_range);
+    assert!(matches!(
+      fresh.load_snapshot(out_of_range.as_slice()),
+      Err(Error::SymOutOfRange)
+    ));
+  }
+  assert_eq!(fresh.syms_reserved(), 0);
+  let mut bad_hash = bytes.clone();
+  bad_hash[28] ^= 1;
+  fix_checksum(&mut bad_hash);
+  assert!(matches!(
+    fresh.load_snapshot(bad_hash.as_slice()),
+    Err(Error::HashMismatch)
+  ));
+  let mut corrupt = bytes.clone();
+  corrupt[24] ^= 1;
+  assert!(matches!(
+    fresh.load_snapshot(corrupt.as_slice()),
+    Err(Error::ChecksumMismatch)
+  ));
+  assert!(matches!(
+    fresh.load_snapshot(&bytes[..10]),
+    Err(Error::UnexpectedEof)
+  ));
+  assert!(matches!(
+    fresh.load_snapshot(&bytes[..bytes.len() - 1]),
+    Err(Error::ChecksumMismatch)
+  ));
+  assert!(fresh.get_interned("snapshot_invalid").is_none());
+
+  // running out of budget is an error, not a panic
+  fresh.set_byte_budget(Some(0));
+  assert!(matches!(
+    fresh.load_snapshot(bytes.as_slice()),
+    Err(Error::Intern(InternError::PageAllocation))
+  ));
+  fresh.set_byte_budget(None);
+  fresh.load_snapshot(bytes.as_slice()).unwrap();
+  assert!(fresh.get_interned("snapshot_invalid").is_some());
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+// note: Miri can't map files
+#[cfg_attr(miri, ignore)]
+fn mmap() {
+  use ::std::io::ErrorKind;
+
+  const COUNT: usize = 10_000;
+  let dir = ::tempfile::tempdir().unwrap();
+
+  {
+    let interner = Interner::open(dir.path()).unwrap();
+    // a second Interner can't open the same pool
+    assert_eq!(
+      Interner::open(dir.path()).err().unwrap().kind(),
+      ErrorKind::WouldBlock
+    );
+    for i in 0..COUNT {
+      let istr = interner.intern(&format!("mmap_{i}"));
+      assert_eq!(istr.id() as usize, i + 1);
+    }
+    interner.flush().unwrap();
+  }
+
+  // the pool spans more than one page
+  let pages = ::std::fs::read_dir(dir.path())
+    .unwrap()
+    .filter(|entry| {
+      entry
+        .as_ref()
+        .unwrap()
+        .path()
+        .extension()
+        .unwrap_or_default()
+        == "page"
+    })
+    .count();
+  assert!(pages > 1);
+
+  {
+    let interner = Interner::open(dir.path()).unwrap();
+    for i in 0..COUNT {
+      let s = format!("mmap_{i}");
+      let istr = interner.get_interned(&s).unwrap();
+      assert_eq!(istr, s.as_str());
+      assert_eq!(istr.id() as usize, i + 1);
+      assert_eq!(istr.as_c_str().to_bytes(), s.as_bytes());
+      assert_eq!(interner.intern(&s), istr);
+      assert_eq!(interner.resolve(i as u32 + 1), Some(istr));
+    }
+    assert_eq!(interner.intern("mmap_new").id() as usize, COUNT + 1);
+  }
+
+  let interner = Interner::open(dir.path()).unwrap();
+  assert_eq!(
+    interner.get_interned("mmap_new").unwrap().id() as usize,
+    COUNT + 1
+  );
+  assert_eq!(
+    interner.collect_interned_strings::<Vec<_>>().len(),
+    COUNT + 1
+  );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+// note: Miri can't map files
+#[cfg_attr(miri, ignore)]
+fn mmap_invalid() {
+  use ::std::{fs, io::ErrorKind};
+
+  let dir = ::tempfile::tempdir().unwrap();
+  Interner::open(dir.path()).unwrap().intern("mmap_invalid");
+  let page = dir.path().join("00000000.page");
+
+  // a corrupt record, flagged as not utf-8
+  let mut bytes = fs::read(&page).unwrap();
+  let original = bytes.clone();
+  let len_index = 16 + interner::SIZE_OF_SYM;
+  let len_word = u32::from_ne_bytes(
+    bytes[len_index..len_index + interner::SIZE_OF_LEN]
+      .try_into()
+      .unwrap(),
+  );
+  bytes[len_index..len_index + interner::SIZE_OF_LEN]
+    .copy_from_slice(&(len_word ^ interner::LEN_NOT_UTF8).to_ne_bytes());
+  fs::write(&page, &bytes).unwrap();
+  assert_eq!(
+    Interner::open(dir.path()).err().unwrap().kind(),
+    ErrorKind::InvalidData
+  );
+
+  // a corrupt sym, far past any gaps left by crashes
+  // note: the record is after the page's 16 byte header
+  bytes = original.clone();
+  bytes[16..16 + interner::SIZE_OF_SYM]
+    .copy_from_slice(&u32::MAX.to_ne_bytes());
+  fs::write(&page, &bytes).unwrap();
+  assert_eq!(
+    Interner::open(dir.path()).err().unwrap().kind(),
+    ErrorKind::InvalidData
+  );
+
+  // a gap left by a crash is fine
+  bytes[16..16 + interner::SIZE_OF_SYM].copy_from_slice(&3_u32.to_ne_bytes());
+  fs::write(&page, &bytes).unwrap();
+  let interner = Interner::open(dir.path()).unwrap();
+  assert_eq!(interner.get_interned("mmap_invalid").unwrap().id(), 3);
+  assert!(interner.resolve(1).is_none());
+  drop(interner);
+
+  // not a page
+  bytes = original;
+  bytes[0] = b'X';
+  fs::write(&page, &bytes).unwrap();
+  assert_eq!(
+    Interner::open(dir.path()).err().unwrap().kind(),
+    ErrorKind::InvalidData
+  );
+}
+
+#[test]
+fn byte_budget() {
+  let interner = Interner::new();
+  assert_eq!(interner.byte_budget(), None);
+
+  // no room for the first page
+  interner.set_byte_budget(Some(0));
+  assert_eq!(
+    interner.try_intern("byte_budget").unwrap_err(),
+    InternError::PageAllocation
+  );
+  // room for the first page, but not the tables
+  interner.set_byte_budget(Some(1024));
+  assert_eq!(
+    interner.try_intern("byte_budget").unwrap_err(),
+    InternError::TableGrowth
+  );
+  assert!(interner.get_interned("byte_budget").is_none());
+
+  // intern strings until the budget runs out
+  interner.set_byte_budget(Some(64 * 1024));
+  assert_eq!(interner.byte_budget(), Some(64 * 1024));
+  let mut count = 0;
+  while let Ok(p) = interner.try_intern(&format!("byte_budget_{count}")) {
+    assert_eq!(p.id(), count + 1);
+    count += 1;
+  }
+  assert!(count > 100);
+  // strings already interned don't need any more memory
+  for i in 0..count {
+    let s = format!("byte_budget_{i}");
+    assert_eq!(interner.try_intern(&s).unwrap().id(), i + 1);
+  }
+
+  // the pool is still usable after running out
+  interner.set_byte_budget(None);
+  for i in 0..count + iterations(1000) as u32 {
+    let s = format!("byte_budget_{i}");
+    assert_eq!(interner.try_intern(&s).unwrap().id(), i + 1);
+    assert_eq!(interner.get_interned(&s).unwrap(), s.as_str());
+  }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn too_long() {
+  use crate::interner::{MAX_LEN, WYHASH_SEED};
+
+  let interner = Interner::new();
+  let long = vec![b'a'; MAX_LEN + 1];
+  // note: the length is checked before the string is hashed
+  assert_eq!(
+    interner.try_intern_ibytes_with_wyhash(&long, 0, false),
+    Err(InternError::TooLong)
+  );
+  let short = b"too_long";
+  let batch = [
+    (&short[..], ::wyhash::wyhash(short, WYHASH_SEED)),
+    (&long[..], 0),
+  ];
+  assert_eq!(
+    interner.try_intern_many_ibytes_with_wyhash(&batch, false),
+    Err(InternError::TooLong)
+  );
+  // nothing in the batch was interned, and no shard was left locked
+  assert!(interner.get_interned("too_long").is_none());
+  assert_eq!(interner.intern("too_long"), "too_long");
+}
+
+#[test]
+fn stats() {
+  let interner = Interner::new();
+  assert_eq!(interner.stats().strings, 0);
+  assert!(interner.stats().pages.is_empty());
+
+  const COUNT: usize = iterations(10_000);
+  let mut payload_bytes = 0;
+  for i in 0..COUNT {
+    let s = format!("stats_{i}");
+    payload_bytes += s.len();
+    interner.intern(&s);
+  }
+  let stats = interner.stats();
+  assert_eq!(stats.strings, COUNT);
+  assert_eq!(stats.payload_bytes, payload_bytes);
+  assert!(stats.pages.len() > 1);
+  assert_eq!(
+    stats.pages.iter().map(|page| page.used).sum::<usize>(),
+    payload_bytes + stats.strings * (interner::SIZE_OF_HEADER + 1)
+  );
+  // note: each shard's last page still has room
+  assert_eq!(
+    stats.wasted_bytes,
+    stats
+      .pages
+      .windows(2)
+      .filter(|pages| pages[0].shard == pages[1].shard)
+      .map(|pages| pages[0].capacity - pages[0].used)
+      .sum::<usize>()
+  );
+  for page in &stats.pages {
+    assert!(page.used <= page.capacity);
+  }
+  assert!(stats.id_map_capacity >= COUNT);
+  assert!(stats.id_map_mut_capacity >= COUNT);
+  assert!(
+    stats.allocated_bytes
+      > stats.pages.iter().map(|page| page.capacity).sum::<usize>()
+  );
+  assert!(stats.thread_epochs >= 1);
+
+  // `Sym`s reserved without a string aren't counted
+  interner.skip_syms_through(COUNT as u32 + 10);
+  interner.intern("stats_after_gap");
+  assert_eq!(interner.stats().strings, COUNT + 1);
+
+  // the global pool
+  intern("stats_global");
+  assert!(crate::stats().strings > 0);
+}
+
+#[cfg(feature = "counters")]
+#[test]
+fn counters() {
+  use ::std::thread;
+
+  let interner = Interner::new();
+  assert_eq!(interner.counters(), Counters::default());
+  interner.intern("counters");
+  interner.intern("counters");
+  interner.get_interned("counters");
+  let counters = interner.counters();
+  assert_eq!(counters.slow_path_inserts, 1);
+  assert_eq!(counters.fast_path_hits, 1);
+
+  // every call to intern is counted exactly once
+  const THREADS: u64 = 8;
+  const COUNT: u64 = iterations(1000) as u64;
+  thread::scope(|scope| {
+    for _ in 0..THREADS {
+      scope.spawn(|| {
+        for i in 0..COUNT {
+          interner.intern(&format!("counters_{i}"));
+        }
+      });
+    }
+  });
+  let counters = interner.counters();
+  assert_eq!(counters.slow_path_inserts, COUNT + 1);
+  assert_eq!(
+    counters.fast_path_hits
+      + counters.slow_path_inserts
+      + counters.pending_add_hits
+      + counters.id_map_mut_hits,
+    THREADS * COUNT + 2
+  );
+  assert!(counters.lock_waits == 0 || counters.lock_wait_nanos > 0);
+
+  intern("counters_global");
+  assert!(crate::counters().slow_path_inserts > 0);
+}
+
+#[cfg(feature = "thread-cache")]
+#[test]
+fn thread_cache() {
+  use ::std::thread;
+
+  // many more strings than the cache has entries, so some share an entry
+  let istrs: Vec<IStr> = (0..iterations(10_000))
+    .map(|i| intern(&format!("thread_cache_{i}")))
+    .collect();
+  for (i, &istr) in istrs.iter().enumerate() {
+    let s = format!("thread_cache_{i}");
+    assert_eq!(get_interned(&s), Some(istr));
+    assert_eq!(intern(&s), istr);
+    assert_eq!(try_intern(&s), Ok(istr));
+  }
+  // a string is only found once it's interned, whatever is in its entry
+  assert_eq!(get_interned("thread_cache_missing"), None);
+
+  // each thread has its own cache, and finds the same `IStr`s
+  thread::scope(|scope| {
+    for _ in 0..4 {
+      scope.spawn(|| {
+        for (i, &istr) in istrs.iter().enumerate().rev() {
+          assert_eq!(intern(&format!("thread_cache_{i}")), istr);
+        }
+      });
+    }
+  });
+}
+
+#[test]
+fn ibytes() {
+  let bytes = intern_bytes(b"ibytes\xff");
+  assert_eq!(bytes, b"ibytes\xff");
+  assert_eq!(bytes.len(), 7);
+  assert_eq!(bytes.as_bytes_with_nul(), b"ibytes\xff\0");
+  assert!(!bytes.is_utf8());
+  assert_eq!(bytes.to_istr(), None);
+  assert!(IStr::try_from(bytes).is_err());
+  assert_eq!(bytes, intern_bytes(&b"ibytes\xff".to_vec()));
+  assert_eq!(get_interned_bytes(b"ibytes\xff"), Some(bytes));
+  assert_eq!(get_interned_bytes(b"ibytes\xfe"), None);
+  assert_eq!(
+    bytes.wyhash(),
+    ::wyhash::wyhash(b"ibytes\xff", interner::WYHASH_SEED)
+  );
+  assert_eq!(format!("{bytes:?}"), r#"IBytes(b"ibytes\xff")"#);
+
+  // interior nulls are fine
+  let nul = IBytes::from(&b"ibytes\0nul"[..]);
+  assert_eq!(nul, b"ibytes\0nul");
+  assert_eq!(nul.to_istr().unwrap(), "ibytes\0nul");
+
+  // strings & byte strings share the pool
+  let istr = intern("ibytes_utf8");
+  let utf8 = intern_bytes(b"ibytes_utf8");
+  assert!(utf8.is_utf8());
+  assert_eq!(utf8.to_istr(), Some(istr));
+  assert_eq!(IStr::try_from(utf8), Ok(istr));
+  assert_eq!(IBytes::from(istr), utf8);
+  assert_eq!(utf8.wyhash(), istr.wyhash());
+  let first = intern_bytes(b"ibytes_bytes_first");
+  assert!(intern("ibytes_bytes_first") == first.to_istr().unwrap());
+
+  // byte strings that aren't valid utf-8 are never seen as strings
+  let sym = Sym::from_u32(istr.sym().to_u32() + 1);
+  assert!(sym.is_none() || sym.unwrap().as_istr().as_bytes() != b"ibytes\xff");
+  let istrs: Vec<IStr> = collect_interned_strings();
+  assert!(istrs.iter().all(|istr| istr.as_bytes() != b"ibytes\xff"));
+}
+
+#[test]
+fn ibytes_snapshot() {
+  let interner = Interner::new();
+  interner.intern("ibytes_snapshot");
+  interner.intern_ibytes(b"ibytes_snapshot\xff");
+  let mut bytes = Vec::new();
+  interner.save_snapshot(&mut bytes).unwrap();
+
+  let fresh = Interner::new();
+  fresh.load_snapshot(bytes.as_slice()).unwrap();
+  assert_eq!(fresh.resolve(1).unwrap(), "ibytes_snapshot");
+  assert_eq!(fresh.resolve(2), None);
+  let b = fresh.get_interned_ibytes(b"ibytes_snapshot\xff").unwrap();
+  assert!(!b.is_utf8());
+  assert_eq!(fresh.stats().strings, 2);
+}
+
+#[test]
+fn os_str() {
+  use ::std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+  };
+
+  let os_str = IOsStr::new(OsStr::new("os_str"));
+  assert_eq!(&*os_str, OsStr::new("os_str"));
+  assert_eq!(os_str, IOsStr::from(OsString::from("os_str")));
+  assert_eq!(os_str.as_c_str(), c"os_str");
+  assert_eq!(format!("{os_str:?}"), r#"IOsStr("os_str")"#);
+
+  let path = IPath::new("os_str/path.rs");
+  assert_eq!(path, IPath::from(Path::new("os_str/path.rs")));
+  assert_eq!(path, IPath::from(PathBuf::from("os_str/path.rs")));
+  assert_eq!(path.file_name().unwrap(), "path.rs");
+  assert_eq!(path.as_c_str(), c"os_str/path.rs");
+  assert_eq!(PathBuf::from(path), PathBuf::from("os_str/path.rs"));
+  assert_eq!(format!("{path:?}"), r#"IPath("os_str/path.rs")"#);
+  // unlike `Path`, the bytes must be identical
+  assert_ne!(path, IPath::new("os_str//path.rs"));
+  assert!(path == Path::new("os_str//path.rs"));
+
+  // paths dedupe against the strings & bytes in the pool
+  let istr = intern("os_str/path.rs");
+  assert_eq!(path.to_istr(), Some(istr));
+  assert_eq!(IPath::from(istr), path);
+  assert_eq!(IOsStr::from(path), IOsStr::from(istr));
+  assert_eq!(path.as_ibytes(), intern_bytes(b"os_str/path.rs"));
+  assert_eq!(path.wyhash(), istr.wyhash());
+
+  #[cfg(unix)]
+  {
+    use ::std::os::unix::ffi::OsStrExt;
+    let not_utf8 = IPath::new(OsStr::from_bytes(b"os_str\xff"));
+    assert_eq!(not_utf8.as_os_str().as_bytes(), b"os_str\xff");
+    assert_eq!(not_utf8.to_istr(), None);
+    assert_eq!(not_utf8.as_ibytes(), intern_bytes(b"os_str\xff"));
+    assert_eq!(not_utf8.as_c_str().to_bytes(), b"os_str\xff");
+  }
+}
+
+#[test]
+fn interior_nul() {
+  let istr = intern("interior\0nul");
+  assert_eq!(istr, "interior\0nul");
+  assert_eq!(istr.try_as_c_str().unwrap_err().nul_position(), 8);
+  assert_eq!(
+    istr.try_as_c_str().unwrap_err().to_string(),
+    "the string contains an interior null byte at position 8"
+  );
+  assert!(::std::panic::catch_unwind(|| istr.as_c_str()).is_err());
+  assert!(::std::panic::catch_unwind(|| CString::from(istr)).is_err());
+  assert!(::std::panic::catch_unwind(|| <&CStr>::from(istr)).is_err());
+  // the comparisons don't stop at the interior null byte
+  assert_ne!(istr, c"interior");
+  assert_ne!(istr, CString::new("interior").unwrap());
+
+  let no_nul = intern("interior_nul");
+  assert_eq!(no_nul.try_as_c_str(), Ok(c"interior_nul"));
+  assert_eq!(no_nul.as_c_str(), c"interior_nul");
+  assert_eq!(<&CStr>::from(no_nul), c"interior_nul");
+  assert_eq!(CString::from(no_nul), no_nul);
+  assert_eq!(AsRef::<CStr>::as_ref(&no_nul), c"interior_nul");
+
+  let path = IPath::new("interior\0nul");
+  assert_eq!(path.try_as_c_str().unwrap_err().nul_position(), 8);
+  let pool = Interner::new();
+  assert!(pool.intern("interior\0nul").try_as_c_str().is_err());
+
+  static_table! {
+    static INTERIOR_NUL_TABLE;
+    const WITH_NUL: IStr = "static\0nul";
+    const WITHOUT_NUL: IStr = "static_nul";
+  }
+  assert_eq!(WITH_NUL.try_as_c_str().unwrap_err().nul_position(), 6);
+  assert_eq!(WITHOUT_NUL.try_as_c_str(), Ok(c"static_nul"));
+}
+
+#[test]
+fn rc_istr() {
+  use ::std::thread;
+
+  let a = RcIStr::new("rc_istr");
+  let a2 = RcIStr::from("rc_istr".to_owned());
+  let b = RcIStr::new("rc_istr_b");
+  assert_eq!(a, a2);
+  assert_ne!(a, b);
+  assert_eq!(a, "rc_istr");
+  assert_eq!(RcIStr::strong_count(&a), 2);
+  assert_eq!(a.wyhash(), IStr::new("rc_istr").wyhash());
+  assert_eq!(format!("{a:?}"), r#"RcIStr("rc_istr")"#);
+  // the pools are separate
+  assert!(get_interned("rc_istr_b").is_none());
+
+  let a3 = a.clone();
+  assert_eq!(RcIStr::strong_count(&a), 3);
+  drop((a2, a3));
+  assert_eq!(RcIStr::strong_count(&a), 1);
+  assert_eq!(RcIStr::get_interned("rc_istr"), Some(a.clone()));
+  drop(a);
+  assert_eq!(RcIStr::get_interned("rc_istr"), None);
+  assert_eq!(RcIStr::strong_count(&RcIStr::new("rc_istr")), 1);
+
+  // promoting a string makes it permanent
+  let istr = b.to_istr();
+  drop(b);
+  assert_eq!(RcIStr::get_interned("rc_istr_b"), None);
+  assert_eq!(get_interned("rc_istr_b"), Some(istr));
+
+  // strings are freed & re-added while other threads hold & drop them
+  let threads: Vec<_> = (0..8)
+    .map(|i| {
+      thread::spawn(move || {
+        for j in 0..iterations(1000) {
+          let s = format!("rc_istr_{}", (i + j) % 16);
+          let rc = RcIStr::new(&s);
+          assert_eq!(rc, s.as_str());
+          let rc2 = rc.clone();
+          drop(rc);
+          assert_eq!(rc2.as_str(), s);
+        }
+      })
+    })
+    .collect();
+  for t in threads {
+    t.join().unwrap();
+  }
+  for i in 0..16 {
+    assert_eq!(RcIStr::get_interned(&format!("rc_istr_{i}")), None);
+  }
+}
+
+#[test]
+fn generation() {
+  let global = intern("generation_global");
+  let gen = Generation::new();
+  let a = gen.intern("generation_local");
+  assert_eq!(a, gen.intern("generation_local"));
+  assert_eq!(a, "generation_local");
+  assert_eq!(gen.get_interned("generation_local"), Some(a));
+  assert_eq!(gen.get_interned("generation_missing"), None);
+  assert!(get_interned("generation_local").is_none());
+  assert_eq!(format!("{a:?}"), r#"GenStr("generation_local")"#);
+
+  // strings in the global pool are found there
+  let g = gen.intern("generation_global");
+  assert_eq!(g, global);
+  assert_eq!(gen.stats().strings, 1);
+
+  // a string added to the global pool later is still equal to the
+  // generation's copy
+  let late = gen.intern("generation_late");
+  let late_global = intern("generation_late");
+  assert_eq!(gen.intern("generation_late"), late);
+  assert_eq!(late, late_global);
+  assert_eq!(late.to_istr(), late_global);
+  assert_ne!(late, gen.intern("generation_other"));
+
+  // handles from separate generations
+  let gen2 = Generation::new();
+  assert_eq!(gen2.intern("generation_local"), a);
+  assert_eq!(gen2.get_interned("generation_local").unwrap(), a);
+}
+
+#[test]
+fn interned() {
+  use ::std::thread;
+
+  let a = Interned::new((intern("interned"), 1_u32));
+  let a2 = Interned::from((intern("interned"), 1_u32));
+  let b = Interned::new((intern("interned"), 2_u32));
+  assert_eq!(a, a2);
+  assert_ne!(a, b);
+  assert_eq!(*a, (intern("interned"), 1));
+  assert_eq!(a.1, 1);
+  assert!(a < b);
+  assert_eq!(
+    Interned::get_interned(&(intern("interned"), 2_u32)),
+    Some(b)
+  );
+  assert_eq!(
+    Interned::<(IStr, u32)>::get_interned(&(intern("x"), 3)),
+    None
+  );
+  assert_eq!(format!("{a:?}"), r#"Interned((IStr("interned"), 1))"#);
+
+  // each type has its own pool
+  let s = Interned::new(String::from("interned"));
+  assert_eq!(Interned::get_interned("interned"), Some(s));
+  assert_eq!(s.to_uppercase(), "INTERNED");
+  assert!(!InternPool::<String>::of().is_empty());
+
+  // interned values can be nested
+  #[derive(Hash, PartialEq, Eq, Debug)]
+  enum Expr {
+    Lit(u32),
+    Add(Interned<Expr>, Interned<Expr>),
+  }
+  let one = Interned::new(Expr::Lit(1));
+  let sum = Interned::new(Expr::Add(one, Interned::new(Expr::Lit(1))));
+  assert_eq!(sum, Interned::new(Expr::Add(one, one)));
+  assert!(matches!(*sum, Expr::Add(l, r) if l == r));
+
+  // a static pool
+  static POINTS: InternPool<(i32, i32)> = InternPool::new();
+  assert!(POINTS.is_empty());
+  let threads: Vec<_> = (0..8)
+    .map(|_| {
+      thread::spawn(|| {
+        (0..iterations(1000) as i32)
+          .map(|i| POINTS.intern((i, -i)))
+          .collect()
+      })
+    })
+    .collect();
+  let results: Vec<Vec<Interned<(i32, i32)>>> =
+    threads.into_iter().map(|t| t.join().unwrap()).collect();
+  assert!(results.iter().all(|r| *r == results[0]));
+  assert_eq!(POINTS.len(), iterations(1000));
+  assert_eq!(POINTS.get_interned(&(5, -5)), Some(results[0][5]));
+  // the static pool is separate from the global pool
+  let global = Interned::new((5, -5));
+  assert_ne!(global, results[0][5]);
+  // equal values from different pools are ordered consistently with `Eq`
+  assert_ne!(global.cmp(&results[0][5]), ::core::cmp::Ordering::Equal);
+  assert_eq!(
+    global.cmp(&results[0][5]),
+    results[0][5].cmp(&global).reverse()
+  );
+  assert!(Interned::new((4, 0)) < results[0][5]);
+}
+
+#[test]
+fn nested_reads() {
+  use {
+    crate::double_table::DoubleTable,
+    ::core::sync::atomic::{AtomicBool, Ordering},
+    ::std::{panic, thread, time::Duration},
+  };
+
+  static STRINGS: InternPool<&'static str> = InternPool::new();
+
+  /// A name, compared by looking its string up in another pool
+  ///
+  /// note: equal names have equal strings, so they hash the same
+  #[allow(clippy::derived_hash_with_manual_eq)]
+  #[derive(Hash, Debug)]
+  struct Name(&'static str);
+  impl PartialEq for Name {
+    fn eq(&self, rhs: &Name) -> bool {
+      STRINGS.get_interned(&self.0) == STRINGS.get_interned(&rhs.0)
+    }
+  }
+  impl Eq for Name {}
+
+  static NAMES: InternPool<Name> = InternPool::new();
+  STRINGS.intern("nested_reads");
+  let name = NAMES.intern(Name("nested_reads"));
+  // the strings interned concurrently swap the tables being looked up
+  thread::scope(|s| {
+    s.spawn(|| {
+      for i in 0..iterations(1000) {
+        STRINGS.intern(format!("nested_reads_{i}").leak());
+      }
+    });
+    for _ in 0..iterations(1000) {
+      assert_eq!(NAMES.intern(Name("nested_reads")), name);
+      assert_eq!(NAMES.get_interned(&Name("nested_reads")), Some(name));
+    }
+  });
+
+  // a writer waits for the outer read, even once a nested read is done
+  let outer = DoubleTable::<u64>::new();
+  let inner = DoubleTable::<u64>::new();
+  let outer_done = AtomicBool::new(false);
+  thread::scope(|s| {
+    outer.read(|_| {
+      inner.read(|_| {});
+      s.spawn(|| {
+        // safety: this is the only writer
+        unsafe {
+          outer.writer_table();
+          outer.publish(1, 1, |&v| v);
+        }
+        assert!(outer_done.load(Ordering::Relaxed));
+      });
+      thread::sleep(Duration::from_millis(20));
+      outer_done.store(true, Ordering::Relaxed);
+    });
+  });
+
+  /// A value whose `Eq` impl interns a new string
+  #[allow(clippy::derived_hash_with_manual_eq)]
+  #[derive(Hash, Debug)]
+  struct Interning(u32);
+  impl PartialEq for Interning {
+    fn eq(&self, rhs: &Interning) -> bool {
+      STRINGS.intern("nested_reads_interning");
+      self.0 == rhs.0
+    }
+  }
+  impl Eq for Interning {}
+
+  static INTERNING: InternPool<Interning> = InternPool::new();
+  INTERNING.intern(Interning(1));
+  assert!(panic::catch_unwind(|| INTERNING.intern(Interning(1))).is_err());
+  // neither pool was left locked or reading
+  assert_eq!(STRINGS.get_interned(&"nested_reads_interning"), None);
+  STRINGS.intern("nested_reads_after");
+  INTERNING.intern(Interning(2));
+}
+
+#[test]
+fn ilist() {
+  use ::std::thread;
+
+  let std = IList::new(&[intern("std")]);
+  let path: IList<IStr> = ["std", "collections", "HashMap"]
+    .into_iter()
+    .map(intern)
+    .collect();
+  assert_eq!(
+    path,
+    IList::new(&[intern("std"), intern("collections"), intern("HashMap")])
+  );
+  assert_eq!(path.len(), 3);
+  assert_eq!(path[2], "HashMap");
+  assert_eq!(
+    format!("{path:?}"),
+    r#"IList([IStr("std"), IStr("collections"), IStr("HashMap")])"#
+  );
+
+  // the parent wasn't interned before the path, so it's looked up
+  assert_eq!(path.parent(), None);
+  let collections = IList::from([intern("std"), intern("collections")]);
+  assert_eq!(path.parent(), Some(collections));
+  // this parent was interned before the list
+  assert_eq!(collections.parent(), Some(std));
+  assert_eq!(std.parent(), None);
+  let empty = IList::<IStr>::new(&[]);
+  assert!(empty.is_empty());
+  assert_eq!(std.parent(), Some(empty));
+  assert_eq!(empty.parent(), None);
+  assert_eq!(IList::<IStr>::get_interned(&[intern("collections")]), None);
+
+  // lists of other interned handles, & lists of lists
+  let syms = IList::new(&[Sym::new("a"), Sym::new("b")]);
+  assert_eq!(syms, IList::from(vec![Sym::new("a"), Sym::new("b")]));
+  assert_eq!(syms, &[Sym::new("a"), Sym::new("b")][..]);
+  let nested = IList::new(&[path, collections]);
+  assert_eq!(nested[0].parent(), Some(nested[1]));
+
+  // a static pool
+  static LISTS: IListPool<u32> = IListPool::new();
+  let threads: Vec<_> = (0..8)
+    .map(|_| {
+      thread::spawn(|| {
+        (0..iterations(1000) as u32)
+          .map(|i| LISTS.intern(&(0..i % 100).collect::<Vec<_>>()))
+          .collect()
+      })
+    })
+    .collect();
+  let results: Vec<Vec<IList<u32>>> =
+    threads.into_iter().map(|t| t.join().unwrap()).collect();
+  assert!(results.iter().all(|r| *r == results[0]));
+  assert_eq!(LISTS.len(), 100);
+  assert_eq!(results[0][99].len(), 99);
+  assert_eq!(results[0][99].parent(), Some(results[0][98]));
+  let global = IList::new(&[0_u32]);
+  let local = results[0][2].parent().unwrap();
+  assert_ne!(global, local);
+  // equal lists from different pools are ordered consistently with `Eq`
+  assert_ne!(global.cmp(&local), ::core::cmp::Ordering::Equal);
+  assert_eq!(global.cmp(&local), local.cmp(&global).reverse());
+  assert!(global < results[0][2]);
+}
@@ -0,0 +1,71 @@
+//! A small per-thread cache in front of the global pool, enabled by the
+//! `thread-cache` feature
+//!
+//! Looking a string up in the pool hashes the whole string with wyhash, and
+//! increments the thread's epoch twice around a probe of the shared table.
+//! The cache is direct-mapped, keyed by a much cheaper hash of the string's
+//! length & ends, so a repeated lookup is a few loads & a comparison, with no
+//! atomics at all.
+//!
+//! Only the global pool is cached, since its strings are never freed, so a
+//! cached [`IStr`] never dangles.
+
+use {crate::IStr, ::core::cell::Cell};
+
+/// log2 of the number of entries in each thread's cache
+const ENTRIES_SHIFT: u32 = 8;
+/// The number of entries in each thread's cache
+const ENTRIES: usize = 1 << ENTRIES_SHIFT;
+
+thread_local! {
+  /// note: the entries don't need dropping, so the cache has no destructor,
+  /// and can be used while the thread is exiting
+  static CACHE: [Cell<Option<IStr>>; ENTRIES] =
+    const { [const { Cell::new(None) }; ENTRIES] };
+}
+
+/// The entry of the cache `s` may be stored in
+///
+/// note: only the length & up to 8 bytes from each end are hashed, which is
+/// enough to tell most tokens apart, and a collision only costs a miss. The
+/// ends are read with overlapping fixed-size loads, like wyhash does for
+/// short strings
+#[inline]
+fn entry(s: &[u8]) -> usize {
+  const K: u64 = 0x9e37_79b9_7f4a_7c15;
+  let len = s.len();
+  let (head, tail) = if len >= 8 {
+    (
+      u64::from_le_bytes(s[..8].try_into().unwrap()),
+      u64::from_le_bytes(s[len - 8..].try_into().unwrap()),
+    )
+  } else if len >= 4 {
+    (
+      u32::from_le_bytes(s[..4].try_into().unwrap()) as u64,
+      u32::from_le_bytes(s[len - 4..].try_into().unwrap()) as u64,
+    )
+  } else if len > 0 {
+    let bytes =
+      (s[0] as u64) << 16 | (s[len / 2] as u64) << 8 | s[len - 1] as u64;
+    (bytes, 0)
+  } else {
+    (0, 0)
+  };
+  let hash = (len as u64 ^ head).wrapping_mul(K);
+  let hash = (hash ^ tail).wrapping_mul(K);
+  (hash >> (u64::BITS - ENTRIES_SHIFT)) as usize
+}
+
+/// Find `s` in this thread's cache
+#[inline]
+pub(crate) fn get(s: &str) -> Option<IStr> {
+  let istr = CACHE.with(|cache| cache[entry(s.as_bytes())].get())?;
+  (istr.as_str() == s).then_some(istr)
+}
+
+/// Store an [`IStr`] of the global pool in this thread's cache, replacing
+/// whichever string was in its entry
+#[inline]
+pub(crate) fn insert(istr: IStr) {
+  CACHE.with(|cache| cache[entry(istr.as_str().as_bytes())].set(Some(istr)));
+}